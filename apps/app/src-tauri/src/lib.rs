@@ -9,8 +9,20 @@ use accessibility::{is_macos_accessibility_enabled, open_apple_accessibility};
 
 pub mod recorder;
 use recorder::{
-    cancel_recording, close_recording_session, close_thread, ensure_thread_initialized,
-    enumerate_recording_devices, init_recording_session, start_recording, stop_recording,
+    cancel_recording, close_recording_session, close_thread, delete_old_recordings,
+    delete_recording, discard_recoverable_recording, ensure_thread_initialized,
+    enumerate_recording_devices, get_device_capabilities, get_recorder_state,
+    init_recording_session, list_recordings, list_recoverable_recordings, pause_recording,
+    recover_partial_recordings, restart_audio_thread, resume_recording, spawn_device_watcher,
+    start_background_recording, start_recording, stop_recording, stop_recording_by_id,
+    test_recording_device, RECORDING_RETENTION,
+};
+
+pub mod transcription;
+use transcription::{
+    delete_transcription_model, download_transcription_model, import_transcription_model,
+    list_transcription_models, set_default_transcription_model, transcribe_local,
+    transcribe_recording, transcription_models_disk_usage, verify_transcription_model,
 };
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -27,13 +39,20 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_window_state::Builder::default().build())
-        .setup(|_| {
-            let _ = ensure_thread_initialized();
+        .setup(|app| {
+            // Runs before the audio thread exists, so no partial file is still being written
+            let _ = recover_partial_recordings(app.handle());
+            let _ = delete_old_recordings(app.handle(), RECORDING_RETENTION);
+            let _ = ensure_thread_initialized(app.handle());
+            let _ = spawn_device_watcher(app.handle().clone());
             Ok(())
         })
-        .on_window_event(|_, event| {
+        .on_window_event(|window, event| {
             if let tauri::WindowEvent::Destroyed = event {
-                let _ = close_thread();
+                // Only the main window closing ends the app
+                if window.label() == "main" {
+                    let _ = close_thread();
+                }
             }
         });
 
@@ -55,24 +74,68 @@ pub fn run() {
         open_apple_accessibility,
         is_macos_accessibility_enabled,
         // Register recorder commands
+        get_recorder_state,
+        restart_audio_thread,
         enumerate_recording_devices,
+        get_device_capabilities,
+        test_recording_device,
         init_recording_session,
         close_recording_session,
         start_recording,
+        pause_recording,
+        resume_recording,
         stop_recording,
         cancel_recording,
+        start_background_recording,
+        list_recordings,
+        stop_recording_by_id,
+        delete_recording,
+        list_recoverable_recordings,
+        discard_recoverable_recording,
+        // Register transcription commands
+        transcribe_recording,
+        transcribe_local,
+        list_transcription_models,
+        download_transcription_model,
+        import_transcription_model,
+        delete_transcription_model,
+        verify_transcription_model,
+        set_default_transcription_model,
+        transcription_models_disk_usage,
     ]);
 
     #[cfg(not(target_os = "macos"))]
     let builder = builder.invoke_handler(tauri::generate_handler![
         write_text,
         // Register recorder commands
+        get_recorder_state,
+        restart_audio_thread,
         enumerate_recording_devices,
+        get_device_capabilities,
+        test_recording_device,
         init_recording_session,
         close_recording_session,
         start_recording,
+        pause_recording,
+        resume_recording,
         stop_recording,
         cancel_recording,
+        start_background_recording,
+        list_recordings,
+        stop_recording_by_id,
+        delete_recording,
+        list_recoverable_recordings,
+        discard_recoverable_recording,
+        // Register transcription commands
+        transcribe_recording,
+        transcribe_local,
+        list_transcription_models,
+        download_transcription_model,
+        import_transcription_model,
+        delete_transcription_model,
+        verify_transcription_model,
+        set_default_transcription_model,
+        transcription_models_disk_usage,
     ]);
 
     builder
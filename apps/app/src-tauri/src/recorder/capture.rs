@@ -0,0 +1,756 @@
+use super::convert::{AudioConverter, TARGET_SAMPLE_RATE};
+use super::events::{
+    emit, AudioChunk, InputLevel, SpeechEnd, SpeechStart, AUDIO_CHUNK_EVENT, INPUT_LEVEL_EVENT,
+    SPEECH_END_EVENT, SPEECH_START_EVENT,
+};
+use super::level::{count_clipped, peak};
+use super::limits::LimitWatch;
+use super::silence::SilenceWatch;
+use super::spill::SessionAudio;
+use super::vad::{VadConfig, VadEvent, VoiceActivityDetector};
+use cpal::{FromSample, Sample};
+use ringbuf::traits::{Consumer, Observer, Producer, Split};
+use ringbuf::{HeapCons, HeapProd, HeapRb};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tauri::AppHandle;
+
+/// How much raw device audio the ring buffer can hold before the consumer
+/// thread falls behind and packets start being dropped.
+const RING_BUFFER_SECONDS: usize = 2;
+
+/// How often the consumer thread drains the ring buffer.
+const DRAIN_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Frames popped from the ring buffer per conversion pass.
+const DRAIN_CHUNK_FRAMES: usize = 1024;
+
+/// Size of each streamed chunk: 100ms of 16kHz mono audio.
+const STREAM_CHUNK_SAMPLES: usize = 1_600;
+
+/// Input level events are emitted once per window of this many milliseconds.
+const LEVEL_WINDOW_MS: usize = 50;
+
+/// Snapshot of capture health for one recording.
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureStats {
+    /// Number of device packets that did not fit in the ring buffer
+    pub overruns: u64,
+    /// Number of raw samples lost to overruns
+    pub dropped_samples: u64,
+    /// Number of raw samples that hit full scale
+    pub clipped_samples: u64,
+}
+
+/// Counters shared between the real-time input callback and the audio thread.
+#[derive(Debug, Default)]
+pub struct CaptureCounters {
+    overruns: AtomicU64,
+    dropped_samples: AtomicU64,
+    clipped_samples: AtomicU64,
+}
+
+impl CaptureCounters {
+    pub fn record_overrun(&self, dropped_samples: usize) {
+        self.overruns.fetch_add(1, Ordering::Relaxed);
+        self.dropped_samples
+            .fetch_add(dropped_samples as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_clipping(&self, clipped_samples: usize) {
+        self.clipped_samples
+            .fetch_add(clipped_samples as u64, Ordering::Relaxed);
+    }
+
+    pub fn reset(&self) {
+        self.overruns.store(0, Ordering::Relaxed);
+        self.dropped_samples.store(0, Ordering::Relaxed);
+        self.clipped_samples.store(0, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> CaptureStats {
+        CaptureStats {
+            overruns: self.overruns.load(Ordering::Relaxed),
+            dropped_samples: self.dropped_samples.load(Ordering::Relaxed),
+            clipped_samples: self.clipped_samples.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Producer half owned by the cpal input callback. Never blocks or allocates.
+pub struct CaptureProducer {
+    producer: HeapProd<f32>,
+    counters: Arc<CaptureCounters>,
+}
+
+impl CaptureProducer {
+    /// Pushes a whole device packet, converting it to f32, or drops it and
+    /// records an overrun if it doesn't fit. Packets are never split so the
+    /// consumer stays frame-aligned.
+    pub fn push<T>(&mut self, data: &[T])
+    where
+        T: Sample,
+        f32: FromSample<T>,
+    {
+        if self.producer.vacant_len() >= data.len() {
+            self.producer
+                .push_iter(data.iter().map(|sample| sample.to_sample::<f32>()));
+        } else {
+            self.counters.record_overrun(data.len());
+        }
+    }
+}
+
+/// Emits converted audio to the frontend in fixed-size chunks while recording.
+pub struct ChunkStream {
+    on_chunk: Box<dyn FnMut(AudioChunk) + Send>,
+    pending: Vec<f32>,
+    sequence: u64,
+}
+
+impl ChunkStream {
+    pub fn new(app: AppHandle) -> Self {
+        Self::with_callback(move |chunk| emit(&app, AUDIO_CHUNK_EVENT, chunk))
+    }
+
+    /// Hands each chunk to `on_chunk` instead of emitting it.
+    fn with_callback(on_chunk: impl FnMut(AudioChunk) + Send + 'static) -> Self {
+        Self {
+            on_chunk: Box::new(on_chunk),
+            pending: Vec::with_capacity(STREAM_CHUNK_SAMPLES * 2),
+            sequence: 0,
+        }
+    }
+
+    fn push(&mut self, samples: &[f32]) {
+        self.pending.extend_from_slice(samples);
+        self.emit_full_chunks();
+    }
+
+    fn emit_full_chunks(&mut self) {
+        while self.pending.len() >= STREAM_CHUNK_SAMPLES {
+            let samples = self.pending.drain(..STREAM_CHUNK_SAMPLES).collect();
+            self.emit_chunk(samples, false);
+        }
+    }
+
+    /// Emits whatever is left as the final, possibly shorter, chunk.
+    fn finish(mut self) {
+        self.emit_full_chunks();
+        let samples = std::mem::take(&mut self.pending);
+        self.emit_chunk(samples, true);
+    }
+
+    /// Drops whatever is left and emits an empty final chunk, so listeners
+    /// still know the stream has ended.
+    fn cancel(mut self) {
+        self.pending.clear();
+        self.emit_chunk(Vec::new(), true);
+    }
+
+    fn emit_chunk(&mut self, samples: Vec<f32>, is_final: bool) {
+        (self.on_chunk)(AudioChunk {
+            sequence: self.sequence,
+            sample_rate: TARGET_SAMPLE_RATE,
+            samples,
+            is_final,
+        });
+        self.sequence += 1;
+    }
+}
+
+/// Level of one window of raw device audio.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Level {
+    rms: f32,
+    peak: f32,
+}
+
+/// Measures the raw device audio before conversion, so peaks and clipping
+/// aren't smoothed away by resampling, and reports one level per window.
+struct LevelMeter {
+    counters: Arc<CaptureCounters>,
+    /// Interleaved samples per window
+    window: usize,
+    samples: usize,
+    sum_squares: f32,
+    peak: f32,
+}
+
+impl LevelMeter {
+    fn new(counters: Arc<CaptureCounters>, input_sample_rate: u32, channels: usize) -> Self {
+        let frames = (input_sample_rate as usize * LEVEL_WINDOW_MS / 1000).max(1);
+        Self {
+            counters,
+            window: frames * channels,
+            samples: 0,
+            sum_squares: 0.0,
+            peak: 0.0,
+        }
+    }
+
+    /// Appends the level of every window `data` completes to `levels`.
+    fn push(&mut self, data: &[f32], levels: &mut Vec<Level>) {
+        let clipped = count_clipped(data);
+        if clipped > 0 {
+            self.counters.record_clipping(clipped);
+        }
+
+        let mut remaining = data;
+        while !remaining.is_empty() {
+            let take = (self.window - self.samples).min(remaining.len());
+            let (window, rest) = remaining.split_at(take);
+            self.sum_squares += window.iter().map(|s| s * s).sum::<f32>();
+            self.peak = self.peak.max(peak(window));
+            self.samples += take;
+            remaining = rest;
+
+            if self.samples == self.window {
+                levels.push(Level {
+                    rms: (self.sum_squares / self.samples as f32).sqrt(),
+                    peak: self.peak,
+                });
+                self.samples = 0;
+                self.sum_squares = 0.0;
+                self.peak = 0.0;
+            }
+        }
+    }
+}
+
+/// Runs voice activity detection on converted audio and emits speech events.
+pub struct VadStream {
+    app: AppHandle,
+    detector: VoiceActivityDetector,
+    events: Vec<VadEvent>,
+    segment: u64,
+}
+
+impl VadStream {
+    pub fn new(app: AppHandle, config: VadConfig) -> Self {
+        Self {
+            app,
+            detector: VoiceActivityDetector::new(config),
+            events: Vec::new(),
+            segment: 0,
+        }
+    }
+
+    fn push(&mut self, samples: &[f32]) {
+        self.detector.process(samples, &mut self.events);
+        self.emit_events();
+    }
+
+    /// Ends a segment that is still in progress.
+    fn finish(mut self) {
+        self.detector.finish(&mut self.events);
+        self.emit_events();
+    }
+
+    fn emit_events(&mut self) {
+        let to_secs = |samples: usize| samples as f32 / TARGET_SAMPLE_RATE as f32;
+        for event in std::mem::take(&mut self.events) {
+            match event {
+                VadEvent::SpeechStart { start } => emit(
+                    &self.app,
+                    SPEECH_START_EVENT,
+                    SpeechStart {
+                        segment: self.segment,
+                        start_secs: to_secs(start),
+                    },
+                ),
+                VadEvent::SpeechEnd { start, samples } => {
+                    emit(
+                        &self.app,
+                        SPEECH_END_EVENT,
+                        SpeechEnd {
+                            segment: self.segment,
+                            start_secs: to_secs(start),
+                            sample_rate: TARGET_SAMPLE_RATE,
+                            samples,
+                        },
+                    );
+                    self.segment += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Everything the consumer thread feeds with converted audio besides the
+/// session audio.
+#[derive(Default)]
+pub struct CaptureOutputs {
+    /// Recording that input levels are reported for
+    recording_id: Option<String>,
+    chunks: Option<ChunkStream>,
+    vad: Option<VadStream>,
+    silence: Option<SilenceWatch>,
+    limit: Option<LimitWatch>,
+}
+
+impl CaptureOutputs {
+    fn push(&mut self, samples: &[f32]) {
+        if let Some(chunks) = self.chunks.as_mut() {
+            chunks.push(samples);
+        }
+        if let Some(vad) = self.vad.as_mut() {
+            vad.push(samples);
+        }
+        if let Some(silence) = self.silence.as_mut() {
+            silence.push(samples);
+        }
+        if let Some(limit) = self.limit.as_mut() {
+            limit.push(samples);
+        }
+    }
+
+    fn finish(&mut self) {
+        self.recording_id = None;
+        if let Some(chunks) = self.chunks.take() {
+            chunks.finish();
+        }
+        if let Some(vad) = self.vad.take() {
+            vad.finish();
+        }
+        self.silence = None;
+        self.limit = None;
+    }
+
+    /// Detaches all outputs of a canceled recording. Chunk listeners get an
+    /// empty final chunk, and a speech segment in progress still ends.
+    fn discard(&mut self) {
+        self.recording_id = None;
+        if let Some(chunks) = self.chunks.take() {
+            chunks.cancel();
+        }
+        if let Some(vad) = self.vad.take() {
+            vad.finish();
+        }
+        self.silence = None;
+        self.limit = None;
+    }
+}
+
+enum DrainCommand {
+    Flush(mpsc::Sender<()>),
+    Discard(mpsc::Sender<()>),
+    SetRecordingId(String),
+    StartStreaming(ChunkStream),
+    StartVad(Box<VadStream>),
+    WatchSilence(SilenceWatch),
+    WatchLimit(LimitWatch),
+    Finish(mpsc::Sender<()>),
+    TakeOutputs(mpsc::Sender<CaptureOutputs>),
+    RestoreOutputs(Box<CaptureOutputs>),
+}
+
+/// Consumer thread that drains the ring buffer, converts the raw device audio
+/// and appends it to the session audio.
+pub struct CaptureConsumer {
+    commands: Option<mpsc::Sender<DrainCommand>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl CaptureConsumer {
+    /// Blocks until everything pushed so far has been converted into the
+    /// session audio.
+    pub fn flush(&self) {
+        let (done_tx, done_rx) = mpsc::channel();
+        if self.send(DrainCommand::Flush(done_tx)) {
+            let _ = done_rx.recv();
+        }
+    }
+
+    /// Tags input level events with `recording_id` until the recording is
+    /// finished or discarded.
+    pub fn set_recording_id(&self, recording_id: String) {
+        self.send(DrainCommand::SetRecordingId(recording_id));
+    }
+
+    /// Starts emitting newly converted audio as chunk events.
+    pub fn start_streaming(&self, stream: ChunkStream) {
+        self.send(DrainCommand::StartStreaming(stream));
+    }
+
+    /// Starts splitting newly converted audio into speech segments.
+    pub fn start_vad(&self, vad: VadStream) {
+        self.send(DrainCommand::StartVad(Box::new(vad)));
+    }
+
+    /// Starts watching newly converted audio for prolonged silence.
+    pub fn watch_silence(&self, watch: SilenceWatch) {
+        self.send(DrainCommand::WatchSilence(watch));
+    }
+
+    /// Starts counting newly converted audio against the session limit.
+    pub fn watch_limit(&self, watch: LimitWatch) {
+        self.send(DrainCommand::WatchLimit(watch));
+    }
+
+    /// Emits the final chunk and any speech segment still in progress, then
+    /// detaches all outputs. Blocks until the events have been emitted.
+    pub fn finish(&self) {
+        let (done_tx, done_rx) = mpsc::channel();
+        if self.send(DrainCommand::Finish(done_tx)) {
+            let _ = done_rx.recv();
+        }
+    }
+
+    /// Detaches the outputs of a canceled recording, emitting an empty final
+    /// chunk and ending any speech segment in progress. Blocks until the
+    /// events have been emitted.
+    pub fn discard(&self) {
+        let (done_tx, done_rx) = mpsc::channel();
+        if self.send(DrainCommand::Discard(done_tx)) {
+            let _ = done_rx.recv();
+        }
+    }
+
+    /// Detaches the active outputs so they can continue on another consumer.
+    pub fn take_outputs(&self) -> CaptureOutputs {
+        let (outputs_tx, outputs_rx) = mpsc::channel();
+        if self.send(DrainCommand::TakeOutputs(outputs_tx)) {
+            outputs_rx.recv().unwrap_or_default()
+        } else {
+            CaptureOutputs::default()
+        }
+    }
+
+    pub fn restore_outputs(&self, outputs: CaptureOutputs) {
+        self.send(DrainCommand::RestoreOutputs(Box::new(outputs)));
+    }
+
+    fn send(&self, command: DrainCommand) -> bool {
+        self.commands
+            .as_ref()
+            .is_some_and(|commands| commands.send(command).is_ok())
+    }
+}
+
+impl Drop for CaptureConsumer {
+    fn drop(&mut self) {
+        // Closing the command channel tells the consumer thread to exit
+        self.commands.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Creates the SPSC pipeline between the input callback and `audio`.
+pub fn capture_pipeline(
+    app: AppHandle,
+    input_sample_rate: u32,
+    input_channels: u16,
+    input_channel: Option<u16>,
+    audio: Arc<Mutex<SessionAudio>>,
+    counters: Arc<CaptureCounters>,
+) -> std::io::Result<(CaptureProducer, CaptureConsumer)> {
+    let channels = input_channels.max(1) as usize;
+    let capacity = input_sample_rate as usize * channels * RING_BUFFER_SECONDS;
+    let (producer, mut consumer) = HeapRb::<f32>::new(capacity).split();
+    let mut converter = AudioConverter::new(input_sample_rate, input_channels, input_channel)
+        .map_err(std::io::Error::other)?;
+    let mut meter = LevelMeter::new(counters.clone(), input_sample_rate, channels);
+
+    let (commands_tx, commands_rx) = mpsc::channel();
+    let handle = std::thread::Builder::new()
+        .name("audio-capture-consumer".to_string())
+        .spawn(move || {
+            let mut scratch = vec![0.0f32; DRAIN_CHUNK_FRAMES * channels];
+            let mut converted = Vec::new();
+            let mut levels = Vec::new();
+            let mut outputs = CaptureOutputs::default();
+            loop {
+                let command = commands_rx.recv_timeout(DRAIN_INTERVAL);
+                // Stopping and pausing need the audio the resampler still holds back
+                let flush = matches!(
+                    command,
+                    Ok(DrainCommand::Flush(_) | DrainCommand::Finish(_))
+                );
+                drain(
+                    &mut consumer,
+                    &mut scratch,
+                    &mut converter,
+                    &mut meter,
+                    &mut converted,
+                    &mut levels,
+                    flush,
+                );
+                for level in levels.drain(..) {
+                    emit(
+                        &app,
+                        INPUT_LEVEL_EVENT,
+                        InputLevel {
+                            recording_id: outputs.recording_id.clone(),
+                            rms: level.rms,
+                            peak: level.peak,
+                        },
+                    );
+                }
+                if !converted.is_empty() {
+                    if let Ok(mut audio) = audio.lock() {
+                        audio.push(&converted);
+                    }
+                    // Feed the outputs outside the audio lock
+                    outputs.push(&converted);
+                    converted.clear();
+                }
+                match command {
+                    Ok(DrainCommand::Flush(done)) => {
+                        let _ = done.send(());
+                    }
+                    Ok(DrainCommand::Discard(done)) => {
+                        converter.reset();
+                        outputs.discard();
+                        let _ = done.send(());
+                    }
+                    Ok(DrainCommand::SetRecordingId(recording_id)) => {
+                        outputs.recording_id = Some(recording_id)
+                    }
+                    Ok(DrainCommand::StartStreaming(chunks)) => outputs.chunks = Some(chunks),
+                    Ok(DrainCommand::StartVad(vad)) => outputs.vad = Some(*vad),
+                    Ok(DrainCommand::WatchSilence(watch)) => outputs.silence = Some(watch),
+                    Ok(DrainCommand::WatchLimit(watch)) => outputs.limit = Some(watch),
+                    Ok(DrainCommand::Finish(done)) => {
+                        outputs.finish();
+                        let _ = done.send(());
+                    }
+                    Ok(DrainCommand::TakeOutputs(outputs_tx)) => {
+                        let _ = outputs_tx.send(std::mem::take(&mut outputs));
+                    }
+                    Ok(DrainCommand::RestoreOutputs(restored)) => outputs = *restored,
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        })?;
+
+    Ok((
+        CaptureProducer { producer, counters },
+        CaptureConsumer {
+            commands: Some(commands_tx),
+            handle: Some(handle),
+        },
+    ))
+}
+
+fn drain(
+    consumer: &mut HeapCons<f32>,
+    scratch: &mut [f32],
+    converter: &mut AudioConverter,
+    meter: &mut LevelMeter,
+    converted: &mut Vec<f32>,
+    levels: &mut Vec<Level>,
+    flush: bool,
+) {
+    while !consumer.is_empty() {
+        let popped = consumer.pop_slice(scratch);
+        if popped == 0 {
+            break;
+        }
+        meter.push(&scratch[..popped], levels);
+        converter.process(&scratch[..popped], converted);
+    }
+    if flush {
+        converter.flush(converted);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn producer(capacity: usize) -> (CaptureProducer, HeapCons<f32>, Arc<CaptureCounters>) {
+        let (producer, consumer) = HeapRb::<f32>::new(capacity).split();
+        let counters = Arc::new(CaptureCounters::default());
+        let producer = CaptureProducer {
+            producer,
+            counters: counters.clone(),
+        };
+        (producer, consumer, counters)
+    }
+
+    fn pop_all(consumer: &mut HeapCons<f32>) -> Vec<f32> {
+        let mut samples = vec![0.0; consumer.occupied_len()];
+        let popped = consumer.pop_slice(&mut samples);
+        samples.truncate(popped);
+        samples
+    }
+
+    #[test]
+    fn packets_that_dont_fit_are_dropped_whole() {
+        let (mut producer, mut consumer, counters) = producer(8);
+        producer.push(&[1.0f32, 2.0, 3.0, 4.0, 5.0]);
+        // Only 3 slots left, so none of this packet goes in
+        producer.push(&[6.0f32, 7.0, 8.0, 9.0]);
+        producer.push(&[6.0f32, 7.0, 8.0]);
+
+        let stats = counters.snapshot();
+        assert_eq!(stats.overruns, 1);
+        assert_eq!(stats.dropped_samples, 4);
+        assert_eq!(
+            pop_all(&mut consumer),
+            [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]
+        );
+    }
+
+    #[test]
+    fn consumer_drains_in_order_across_wraparound() {
+        let (mut producer, mut consumer, counters) = producer(6);
+        let mut drained = Vec::new();
+        for packet in 0..10 {
+            let base = packet as f32 * 4.0;
+            producer.push(&[base, base + 1.0, base + 2.0, base + 3.0]);
+            drained.extend(pop_all(&mut consumer));
+        }
+
+        let expected: Vec<f32> = (0..40).map(|i| i as f32).collect();
+        assert_eq!(drained, expected);
+        assert_eq!(counters.snapshot().overruns, 0);
+    }
+
+    #[test]
+    fn every_overrun_is_counted_until_reset() {
+        let (mut producer, _consumer, counters) = producer(4);
+        producer.push(&[0.0f32; 4]);
+        for _ in 0..3 {
+            producer.push(&[0.0f32; 2]);
+        }
+        let stats = counters.snapshot();
+        assert_eq!((stats.overruns, stats.dropped_samples), (3, 6));
+
+        counters.reset();
+        let stats = counters.snapshot();
+        assert_eq!((stats.overruns, stats.dropped_samples), (0, 0));
+    }
+
+    #[test]
+    fn integer_samples_are_converted_on_push() {
+        let (mut producer, mut consumer, _) = producer(4);
+        producer.push(&[i16::MIN, 0, i16::MAX / 2 + 1]);
+        assert_eq!(pop_all(&mut consumer), [-1.0, 0.0, 0.5]);
+    }
+
+    fn chunk_stream() -> (ChunkStream, Arc<Mutex<Vec<AudioChunk>>>) {
+        let chunks = Arc::new(Mutex::new(Vec::new()));
+        let sink = chunks.clone();
+        let stream = ChunkStream::with_callback(move |chunk| sink.lock().unwrap().push(chunk));
+        (stream, chunks)
+    }
+
+    /// Sequence number, length and final flag of each chunk.
+    fn layout(chunks: &Mutex<Vec<AudioChunk>>) -> Vec<(u64, usize, bool)> {
+        chunks
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|chunk| (chunk.sequence, chunk.samples.len(), chunk.is_final))
+            .collect()
+    }
+
+    #[test]
+    fn chunks_have_a_fixed_size_and_count_up() {
+        let (mut stream, chunks) = chunk_stream();
+        let samples: Vec<f32> = (0..4_000).map(|i| i as f32).collect();
+        for part in [&samples[..1_000], &samples[1_000..2_000], &samples[2_000..]] {
+            stream.push(part);
+        }
+        assert_eq!(layout(&chunks), [(0, 1_600, false), (1, 1_600, false)]);
+
+        stream.finish();
+        assert_eq!(
+            layout(&chunks),
+            [(0, 1_600, false), (1, 1_600, false), (2, 800, true)]
+        );
+        let streamed: Vec<f32> = chunks
+            .lock()
+            .unwrap()
+            .iter()
+            .flat_map(|chunk| chunk.samples.clone())
+            .collect();
+        assert_eq!(streamed, samples);
+    }
+
+    #[test]
+    fn final_chunk_is_empty_when_nothing_is_left() {
+        let (mut stream, chunks) = chunk_stream();
+        stream.push(&[0.0; STREAM_CHUNK_SAMPLES * 2]);
+        stream.finish();
+        assert_eq!(
+            layout(&chunks),
+            [(0, 1_600, false), (1, 1_600, false), (2, 0, true)]
+        );
+    }
+
+    #[test]
+    fn cancel_drops_pending_audio_and_ends_the_stream() {
+        let (mut stream, chunks) = chunk_stream();
+        stream.push(&[0.5; 2_000]);
+        stream.cancel();
+        assert_eq!(layout(&chunks), [(0, 1_600, false), (1, 0, true)]);
+    }
+
+    #[test]
+    fn discarded_outputs_stream_nothing_further() {
+        let (stream, chunks) = chunk_stream();
+        let mut outputs = CaptureOutputs {
+            chunks: Some(stream),
+            ..Default::default()
+        };
+        outputs.push(&[0.0; STREAM_CHUNK_SAMPLES]);
+        outputs.discard();
+        outputs.push(&[0.0; STREAM_CHUNK_SAMPLES * 3]);
+        outputs.finish();
+        assert_eq!(layout(&chunks), [(0, 1_600, false), (1, 0, true)]);
+    }
+
+    fn assert_level(level: Level, rms: f32, peak: f32) {
+        assert!((level.rms - rms).abs() < 1e-5, "{:?}", level);
+        assert!((level.peak - peak).abs() < 1e-6, "{:?}", level);
+    }
+
+    #[test]
+    fn levels_cover_interleaved_windows_across_pushes() {
+        let counters = Arc::new(CaptureCounters::default());
+        // 50 stereo frames per window at 1 kHz
+        let mut meter = LevelMeter::new(counters.clone(), 1_000, 2);
+        let mut levels = Vec::new();
+        meter.push(&[0.5; 60], &mut levels);
+        assert!(levels.is_empty());
+        meter.push(&[-1.0; 60], &mut levels);
+        meter.push(&[0.0; 80], &mut levels);
+
+        assert_eq!(levels.len(), 2);
+        assert_level(levels[0], (0.55f32).sqrt(), 1.0);
+        assert_level(levels[1], (0.2f32).sqrt(), 1.0);
+        assert_eq!(counters.snapshot().clipped_samples, 60);
+    }
+
+    #[test]
+    fn each_window_starts_from_silence() {
+        let mut meter = LevelMeter::new(Arc::default(), 1_000, 1);
+        let mut levels = Vec::new();
+        meter.push(&[0.8; 50], &mut levels);
+        meter.push(&[0.0; 50], &mut levels);
+        assert_level(levels[0], 0.8, 0.8);
+        assert_level(levels[1], 0.0, 0.0);
+    }
+
+    #[test]
+    fn levels_are_reported_once_per_window() {
+        let mut meter = LevelMeter::new(Arc::default(), 48_000, 1);
+        let mut levels = Vec::new();
+        // One second in 10ms device packets
+        for _ in 0..100 {
+            meter.push(&[0.1; 480], &mut levels);
+        }
+        assert_eq!(levels.len(), 1000 / LEVEL_WINDOW_MS);
+    }
+}
@@ -1,269 +1,671 @@
-use super::thread::{spawn_audio_thread, AudioCommand, AudioResponse};
+use super::config::{CaptureConfig, DeviceCapabilities, DeviceTestReport, RequestedConfig};
+use super::devices::DeviceInfo;
+use super::error::{RecorderError, Result};
+use super::events::{emit, RecorderReset, RECORDER_RESET_EVENT};
+use super::level::{peak, rms};
+use super::limits::RecordingLimits;
+use super::recovery::{discard_recoverable, list_recoverable, RecoverableRecording};
+use super::silence::SilenceConfig;
+use super::spill::{delete_recording_file, RecordingFile};
+use super::state::{RecorderState, RECORDER_STATE_CHANGED_EVENT};
+use super::thread::{
+    spawn_audio_thread, ActiveRecording, AudioCommand, AudioRequest, AudioResponse, AudioThread,
+    MAX_TEST_SECONDS,
+};
+use super::vad::VadConfig;
+use super::wav::encode_wav_pcm16;
+use base64::prelude::*;
 use once_cell::sync::Lazy;
-use serde::Serialize;
-use std::sync::mpsc::{self, Receiver, Sender};
+use std::any::Any;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::sync::Mutex;
-use thiserror::Error;
-use tracing::{debug, error, info};
-
-// Global static mutex to hold the audio thread sender and state
-static AUDIO_THREAD: Lazy<Mutex<Option<(Sender<AudioCommand>, Receiver<AudioResponse>)>>> =
-    Lazy::new(|| Mutex::new(None));
-
-// Track current recording state
-static IS_RECORDING: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
-
-#[derive(Debug, Error, Serialize)]
-pub enum RecorderError {
-    #[error("Audio thread not initialized")]
-    ThreadNotInitialized,
-    #[error("Failed to send command: {0}")]
-    SendError(String),
-    #[error("Failed to receive response: {0}")]
-    ReceiveError(String),
-    #[error("Audio error: {0}")]
-    AudioError(String),
-    #[error("No active recording")]
-    NoActiveRecording,
-    #[error("Failed to acquire lock: {0}")]
-    LockError(String),
-}
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+use tracing::{debug, error, info, warn};
+
+/// How long a command waits for the audio thread before reporting it as hung.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Stopping a recording may rewrite its whole file, so it gets longer.
+const SAVE_TIMEOUT: Duration = Duration::from_secs(60);
 
-pub type Result<T> = std::result::Result<T, RecorderError>;
+// Global static mutex to hold the handle of the current audio thread. It's
+// only held long enough to clone the handle, never while waiting for a reply.
+static AUDIO_THREAD: Lazy<Mutex<Option<AudioThread>>> = Lazy::new(|| Mutex::new(None));
 
-pub fn ensure_thread_initialized() -> Result<()> {
-    debug!("Ensuring thread is initialized...");
+/// IDs that tell requests to the audio thread apart in logs. 0 is reserved
+/// for the notifications it sends itself.
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+pub fn ensure_thread_initialized(app: &AppHandle) -> Result<()> {
+    audio_thread(app).map(|_| ())
+}
+
+/// Returns the running audio thread, spawning one if there is none.
+fn audio_thread(app: &AppHandle) -> Result<AudioThread> {
     let mut thread = AUDIO_THREAD
         .lock()
         .map_err(|e| RecorderError::LockError(e.to_string()))?;
 
-    if thread.is_some() {
-        debug!("Thread already initialized");
-        return Ok(());
+    if let Some(thread) = thread.as_ref() {
+        return Ok(thread.clone());
     }
 
     debug!("Thread not initialized, creating new audio thread...");
-    let (response_tx, response_rx) = mpsc::channel();
+    let spawned = spawn_supervised(app)?;
+    *thread = Some(spawned.clone());
+
+    info!("Audio thread created successfully");
+    Ok(spawned)
+}
 
-    let command_tx =
-        spawn_audio_thread(response_tx).map_err(|e| RecorderError::SendError(e.to_string()))?;
+/// Spawns an audio thread along with a supervisor that replaces it if it dies.
+fn spawn_supervised(app: &AppHandle) -> Result<AudioThread> {
+    let (thread, handle) =
+        spawn_audio_thread(app.clone()).map_err(|e| RecorderError::SendError(e.to_string()))?;
 
-    *thread = Some((command_tx, response_rx));
+    let supervised = thread.clone();
+    let app = app.clone();
+    supervise(handle, move |reason| {
+        replace_dead_thread(&app, &supervised, reason)
+    })
+    .map_err(|e| RecorderError::SendError(e.to_string()))?;
+    Ok(thread)
+}
 
-    info!("Audio thread created successfully");
+/// Waits on a supervisor thread for the thread behind `handle` to exit, then
+/// calls `on_exit` with the reason, e.g. its panic message.
+fn supervise(
+    handle: JoinHandle<()>,
+    on_exit: impl FnOnce(String) + Send + 'static,
+) -> std::io::Result<()> {
+    std::thread::Builder::new()
+        .name("audio-thread-supervisor".to_string())
+        .spawn(move || {
+            let reason = match handle.join() {
+                Ok(()) => "The audio thread exited unexpectedly".to_string(),
+                Err(panic) => panic_message(&*panic),
+            };
+            on_exit(reason);
+        })?;
     Ok(())
 }
 
-fn with_thread<F, T>(f: F) -> Result<T>
-where
-    F: FnOnce(&Sender<AudioCommand>, &Receiver<AudioResponse>) -> Result<T>,
-{
-    ensure_thread_initialized()?;
-    let thread = AUDIO_THREAD
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "The audio thread panicked".to_string())
+}
+
+/// Puts a thread from `respawn` in `slot` if it still holds the thread
+/// `is_dead` matches. Threads closed or replaced on purpose are already gone
+/// from the slot and stay that way. Returns whether the slot was changed.
+fn replace_if_current<T>(
+    slot: &Mutex<Option<T>>,
+    is_dead: impl Fn(&T) -> bool,
+    respawn: impl FnOnce() -> Option<T>,
+) -> bool {
+    let Ok(mut current) = slot.lock() else {
+        return false;
+    };
+    if !current.as_ref().is_some_and(is_dead) {
+        return false;
+    }
+    *current = respawn();
+    true
+}
+
+/// Respawns the audio thread after `dead` exited, unless it had already been
+/// closed or replaced on purpose, and tells the frontend the recorder was reset.
+fn replace_dead_thread(app: &AppHandle, dead: &AudioThread, reason: String) {
+    let replaced = replace_if_current(
+        &AUDIO_THREAD,
+        |thread| thread.is_same(dead),
+        || {
+            error!("Audio thread died, restarting it: {}", reason);
+            // If this fails too, the next command tries again
+            spawn_supervised(app)
+                .inspect_err(|e| error!("Failed to restart audio thread: {}", e))
+                .ok()
+        },
+    );
+    if !replaced {
+        return;
+    }
+
+    // The new thread starts without a session
+    emit(app, RECORDER_STATE_CHANGED_EVENT, RecorderState::Idle);
+    emit(app, RECORDER_RESET_EVENT, RecorderReset { reason });
+}
+
+/// Sends `command` to `thread` and waits up to `timeout` for its reply. The
+/// wait happens on the blocking pool so a hung audio thread never stalls the
+/// async runtime.
+async fn send_request(
+    thread: &AudioThread,
+    command: AudioCommand,
+    timeout: Duration,
+) -> Result<AudioResponse> {
+    let id = NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+    let (reply_tx, reply_rx) = mpsc::channel();
+    debug!("Sending request {}: {:?}", id, command);
+    thread.send(AudioRequest {
+        id,
+        command,
+        reply: Some(reply_tx),
+    })?;
+
+    let reply = tauri::async_runtime::spawn_blocking(move || reply_rx.recv_timeout(timeout))
+        .await
+        .map_err(|e| RecorderError::ReceiveError(e.to_string()))?;
+    match reply {
+        Ok(response) => Ok(response),
+        Err(RecvTimeoutError::Timeout) => {
+            error!(
+                "Audio thread did not answer request {} within {:?}",
+                id, timeout
+            );
+            Err(RecorderError::Timeout(timeout))
+        }
+        Err(RecvTimeoutError::Disconnected) => Err(RecorderError::ReceiveError(
+            "The audio thread exited without answering".to_string(),
+        )),
+    }
+}
+
+async fn request(app: &AppHandle, command: AudioCommand) -> Result<AudioResponse> {
+    request_with_timeout(app, command, COMMAND_TIMEOUT).await
+}
+
+async fn request_with_timeout(
+    app: &AppHandle,
+    command: AudioCommand,
+    timeout: Duration,
+) -> Result<AudioResponse> {
+    let thread = audio_thread(app)?;
+    send_request(&thread, command, timeout).await
+}
+
+/// Replaces an audio thread that stopped responding. Sessions and recordings
+/// on the old thread are abandoned; their partial files are recovered on the
+/// next launch.
+#[tauri::command]
+pub async fn restart_audio_thread(app: AppHandle) -> Result<()> {
+    let old = AUDIO_THREAD
         .lock()
-        .map_err(|e| RecorderError::LockError(e.to_string()))?;
-    let (tx, rx) = thread.as_ref().ok_or(RecorderError::ThreadNotInitialized)?;
-    f(tx, rx)
+        .map_err(|e| RecorderError::LockError(e.to_string()))?
+        .take();
+    if let Some(old) = old {
+        warn!("Restarting the audio thread");
+        old.retire();
+    }
+    audio_thread(&app)?;
+    // The new thread starts without a session
+    emit(&app, RECORDER_STATE_CHANGED_EVENT, RecorderState::Idle);
+    Ok(())
 }
 
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct DeviceInfo {
-    device_id: String,
-    label: String,
+#[tauri::command]
+pub async fn get_recorder_state(app: AppHandle) -> Result<RecorderState> {
+    match request(&app, AudioCommand::GetRecorderState).await? {
+        AudioResponse::RecorderState(state) => Ok(state),
+        AudioResponse::Error(e) => Err(e.into()),
+        _ => Err(RecorderError::UnexpectedResponse),
+    }
 }
 
 #[tauri::command]
-pub async fn enumerate_recording_devices() -> Result<Vec<DeviceInfo>> {
+pub async fn enumerate_recording_devices(app: AppHandle) -> Result<Vec<DeviceInfo>> {
     debug!("Enumerating recording devices");
-    with_thread(|tx, rx| {
-        tx.send(AudioCommand::EnumerateRecordingDevices)
-            .map_err(|e| RecorderError::SendError(e.to_string()))?;
-
-        match rx.recv() {
-            Ok(AudioResponse::RecordingDeviceList(devices)) => {
-                info!("Found {} recording devices", devices.len());
-                Ok(devices
-                    .into_iter()
-                    .map(|label| DeviceInfo {
-                        device_id: label.clone(),
-                        label,
-                    })
-                    .collect())
-            }
-            Ok(AudioResponse::Error(e)) => {
-                error!("Failed to enumerate devices: {}", e);
-                Err(RecorderError::AudioError(e))
-            }
-            Ok(_) => {
-                error!("Unexpected response while enumerating devices");
-                Err(RecorderError::AudioError("Unexpected response".to_string()))
-            }
-            Err(e) => {
-                error!("Failed to receive device enumeration response: {}", e);
-                Err(RecorderError::ReceiveError(e.to_string()))
-            }
+    match request(&app, AudioCommand::EnumerateRecordingDevices).await? {
+        AudioResponse::RecordingDeviceList(devices) => {
+            info!("Found {} recording devices", devices.len());
+            Ok(devices)
         }
-    })
+        AudioResponse::Error(e) => {
+            error!("Failed to enumerate devices: {}", e);
+            Err(e.into())
+        }
+        _ => {
+            error!("Unexpected response while enumerating devices");
+            Err(RecorderError::UnexpectedResponse)
+        }
+    }
+}
+
+/// Returns the sample formats, rate ranges and channel counts `device_id` supports.
+#[tauri::command]
+pub async fn get_device_capabilities(
+    app: AppHandle,
+    device_id: String,
+) -> Result<DeviceCapabilities> {
+    match request(&app, AudioCommand::GetDeviceCapabilities(device_id)).await? {
+        AudioResponse::DeviceCapabilities(capabilities) => Ok(capabilities),
+        AudioResponse::Error(e) => {
+            error!("Failed to get device capabilities: {}", e);
+            Err(e.into())
+        }
+        _ => Err(RecorderError::UnexpectedResponse),
+    }
 }
 
+/// Opens `device_id` for recording and returns the config the stream was opened with.
+///
+/// `requested_config` is honored when the device supports it, otherwise a
+/// voice-friendly config is chosen. With `failover_to_default` set, a device
+/// that disappears mid-session is replaced by the system default input.
+/// Recordings that reach `limits` stop capturing and emit
+/// `recording-auto-stopped`; without it they are uncapped.
 #[tauri::command]
-pub async fn init_recording_session(device_name: String) -> Result<()> {
+pub async fn init_recording_session(
+    app: AppHandle,
+    device_id: String,
+    requested_config: Option<RequestedConfig>,
+    failover_to_default: Option<bool>,
+    limits: Option<RecordingLimits>,
+) -> Result<CaptureConfig> {
     info!(
-        "Starting init_recording_session with device_name: {}",
-        device_name
+        "Starting init_recording_session with device_id: {}",
+        device_id
     );
-    with_thread(|tx, rx| {
-        debug!("Sending InitRecordingSession command...");
-        tx.send(AudioCommand::InitRecordingSession(device_name))
-            .map_err(|e| RecorderError::SendError(e.to_string()))?;
-
-        debug!("Waiting for response...");
-        match rx.recv() {
-            Ok(AudioResponse::Success(_)) => {
-                info!("Recording session initialized successfully");
-                Ok(())
-            }
-            Ok(AudioResponse::Error(e)) => {
-                error!("Failed to initialize recording session: {}", e);
-                Err(RecorderError::AudioError(e))
-            }
-            Ok(_) => {
-                error!("Unexpected response during initialization");
-                Err(RecorderError::AudioError("Unexpected response".to_string()))
-            }
-            Err(e) => {
-                error!("Failed to receive initialization response: {}", e);
-                Err(RecorderError::ReceiveError(e.to_string()))
-            }
+    match request(
+        &app,
+        AudioCommand::InitRecordingSession {
+            device_id,
+            requested_config,
+            failover_to_default: failover_to_default.unwrap_or(false),
+            limits: limits.unwrap_or_default(),
+        },
+    )
+    .await?
+    {
+        AudioResponse::SessionInitialized(config) => {
+            info!(
+                "Recording session initialized successfully ({} Hz, {} channels, {})",
+                config.sample_rate, config.channels, config.sample_format
+            );
+            Ok(config)
         }
-    })
+        AudioResponse::InvalidTransition(e) => Err(e.into()),
+        AudioResponse::Error(e) => {
+            error!("Failed to initialize recording session: {}", e);
+            Err(e.into())
+        }
+        _ => {
+            error!("Unexpected response during initialization");
+            Err(RecorderError::UnexpectedResponse)
+        }
+    }
 }
 
+/// Closes the session and its stream. Rejected while recording, so a
+/// recording has to be stopped or canceled first.
 #[tauri::command]
-pub async fn close_recording_session() -> Result<()> {
-    with_thread(|tx, rx| {
-        tx.send(AudioCommand::CloseRecordingSession)
-            .map_err(|e| RecorderError::SendError(e.to_string()))?;
-
-        match rx.recv() {
-            Ok(AudioResponse::Success(_)) => {
-                *IS_RECORDING.lock().unwrap() = false;
-                Ok(())
-            }
-            Ok(AudioResponse::Error(e)) => Err(RecorderError::AudioError(e)),
-            Ok(_) => Err(RecorderError::AudioError("Unexpected response".to_string())),
-            Err(e) => Err(RecorderError::ReceiveError(e.to_string())),
-        }
-    })
+pub async fn close_recording_session(app: AppHandle) -> Result<()> {
+    match request(&app, AudioCommand::CloseRecordingSession).await? {
+        AudioResponse::Success(_) => Ok(()),
+        AudioResponse::InvalidTransition(e) => Err(e.into()),
+        AudioResponse::Error(e) => Err(e.into()),
+        _ => Err(RecorderError::UnexpectedResponse),
+    }
 }
 
-pub async fn close_thread() -> Result<()> {
-    let mut thread = AUDIO_THREAD
+/// Asks the audio thread to close its streams and exit. Doesn't wait for it,
+/// so a hung thread can't hold up shutdown; recordings it never got to close
+/// are recovered on the next launch.
+pub fn close_thread() -> Result<()> {
+    let thread = AUDIO_THREAD
         .lock()
-        .map_err(|e| RecorderError::LockError(e.to_string()))?;
+        .map_err(|e| RecorderError::LockError(e.to_string()))?
+        .take();
+
+    let Some(thread) = thread else {
+        debug!("No audio thread to close");
+        return Ok(());
+    };
+    thread.send(AudioCommand::CloseThread.into())?;
+    info!("Asked the audio thread to close");
+    Ok(())
+}
 
-    if let Some((tx, rx)) = thread.take() {
-        debug!("Sending CloseThread command...");
-        tx.send(AudioCommand::CloseThread)
-            .map_err(|e| RecorderError::SendError(e.to_string()))?;
+/// Starts recording `recording_id`, writing it to a file in the app data dir as
+/// it's captured. With `stream_chunks` set, the audio is also emitted as
+/// `recording-audio-chunk` events while it's being captured. With `vad` set,
+/// it's split into speech segments emitted as `speech-start` / `speech-end`.
+/// `silence` controls trimming of the returned audio and auto-stop, which
+/// emits `recording-auto-stopped`.
+#[tauri::command]
+pub async fn start_recording(
+    app: AppHandle,
+    recording_id: Option<String>,
+    stream_chunks: Option<bool>,
+    vad: Option<VadConfig>,
+    silence: Option<SilenceConfig>,
+) -> Result<()> {
+    let stream_chunks = stream_chunks.unwrap_or(false);
+    let recording_id = recording_id.unwrap_or_else(|| {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        format!("recording-{}", now.as_millis())
+    });
+    match request(
+        &app,
+        AudioCommand::StartRecording {
+            recording_id,
+            stream_chunks,
+            vad,
+            silence,
+        },
+    )
+    .await?
+    {
+        AudioResponse::Success(_) => Ok(()),
+        AudioResponse::InvalidTransition(e) => Err(e.into()),
+        AudioResponse::Error(e) => Err(e.into()),
+        _ => Err(RecorderError::UnexpectedResponse),
+    }
+}
 
-        match rx.recv() {
-            Ok(AudioResponse::Success(_)) => {
-                info!("Audio thread closed successfully");
-                Ok(())
-            }
-            Ok(AudioResponse::Error(e)) => {
-                error!("Error closing audio thread: {}", e);
-                Err(RecorderError::AudioError(e))
-            }
-            Ok(_) => {
-                error!("Unexpected response while closing thread");
-                Err(RecorderError::AudioError("Unexpected response".to_string()))
-            }
-            Err(e) => {
-                error!("Failed to receive thread close response: {}", e);
-                Err(RecorderError::ReceiveError(e.to_string()))
+/// Pauses the recording without discarding anything captured so far. With
+/// `mark_segment` set (the default), the pause position is reported in the
+/// `segmentMarkers` of the stopped recording.
+#[tauri::command]
+pub async fn pause_recording(app: AppHandle, mark_segment: Option<bool>) -> Result<()> {
+    debug!("Pausing recording");
+    match request(
+        &app,
+        AudioCommand::PauseRecording {
+            mark_segment: mark_segment.unwrap_or(true),
+        },
+    )
+    .await?
+    {
+        AudioResponse::Success(_) => Ok(()),
+        AudioResponse::InvalidTransition(e) => Err(e.into()),
+        AudioResponse::Error(e) => Err(e.into()),
+        _ => Err(RecorderError::UnexpectedResponse),
+    }
+}
+
+/// Resumes a paused recording, appending to the audio captured before the
+/// pause. Rejected once the recording has stopped on its own.
+#[tauri::command]
+pub async fn resume_recording(app: AppHandle) -> Result<()> {
+    debug!("Resuming recording");
+    match request(&app, AudioCommand::ResumeRecording).await? {
+        AudioResponse::Success(_) => Ok(()),
+        AudioResponse::InvalidTransition(e) => Err(e.into()),
+        AudioResponse::Error(e) => Err(e.into()),
+        _ => Err(RecorderError::UnexpectedResponse),
+    }
+}
+
+/// Stops the recording and returns the 16 kHz mono WAV file it was saved to
+/// in the app data dir.
+#[tauri::command]
+pub async fn stop_recording(app: AppHandle) -> Result<RecordingFile> {
+    debug!("Stopping recording");
+    match request_with_timeout(&app, AudioCommand::StopRecording, SAVE_TIMEOUT).await? {
+        AudioResponse::RecordingSaved(file, stats) => {
+            if stats.overruns > 0 {
+                warn!(
+                    "Audio capture overran {} times ({} samples dropped)",
+                    stats.overruns, stats.dropped_samples
+                );
             }
+            info!(
+                "Recording stopped successfully ({:.2} seconds, saved to {})",
+                file.duration_secs,
+                file.path.display()
+            );
+            Ok(file)
+        }
+        AudioResponse::InvalidTransition(e) => Err(e.into()),
+        AudioResponse::Error(e) => {
+            error!("Failed to stop recording: {}", e);
+            Err(e.into())
+        }
+        _ => {
+            error!("Unexpected response while stopping recording");
+            Err(RecorderError::UnexpectedResponse)
         }
-    } else {
-        debug!("No audio thread to close");
-        Ok(())
     }
 }
 
+/// Records on `device_id` alongside the main session, e.g. a long meeting
+/// capture while dictating on another device. The recording runs until
+/// `stop_recording_by_id` is called with `recording_id`.
 #[tauri::command]
-pub async fn start_recording() -> Result<()> {
-    with_thread(|tx, rx| {
-        tx.send(AudioCommand::StartRecording)
-            .map_err(|e| RecorderError::SendError(e.to_string()))?;
-
-        match rx.recv() {
-            Ok(AudioResponse::Success(_)) => {
-                *IS_RECORDING.lock().unwrap() = true;
-                Ok(())
-            }
-            Ok(AudioResponse::Error(e)) => Err(RecorderError::AudioError(e)),
-            Ok(_) => Err(RecorderError::AudioError("Unexpected response".to_string())),
-            Err(e) => Err(RecorderError::ReceiveError(e.to_string())),
+pub async fn start_background_recording(
+    app: AppHandle,
+    recording_id: String,
+    device_id: String,
+    requested_config: Option<RequestedConfig>,
+    failover_to_default: Option<bool>,
+    limits: Option<RecordingLimits>,
+    silence: Option<SilenceConfig>,
+) -> Result<CaptureConfig> {
+    debug!(
+        "Starting background recording {} on {}",
+        recording_id, device_id
+    );
+    match request(
+        &app,
+        AudioCommand::StartBackgroundRecording {
+            recording_id,
+            device_id,
+            requested_config,
+            failover_to_default: failover_to_default.unwrap_or(false),
+            limits: limits.unwrap_or_default(),
+            silence,
+        },
+    )
+    .await?
+    {
+        AudioResponse::SessionInitialized(config) => Ok(config),
+        AudioResponse::InvalidTransition(e) => Err(e.into()),
+        AudioResponse::Error(e) => {
+            error!("Failed to start background recording: {}", e);
+            Err(e.into())
         }
-    })
+        _ => Err(RecorderError::UnexpectedResponse),
+    }
 }
 
+/// Lists the main session's recording, if one is in progress, and every
+/// background recording.
 #[tauri::command]
-pub async fn stop_recording() -> Result<Vec<f32>> {
-    debug!("Stopping recording");
-    with_thread(|tx, rx| {
-        tx.send(AudioCommand::StopRecording)
-            .map_err(|e| RecorderError::SendError(e.to_string()))?;
-
-        match rx.recv() {
-            Ok(AudioResponse::AudioData(data)) => {
-                *IS_RECORDING.lock().unwrap() = false;
-                info!("Recording stopped successfully ({} samples)", data.len());
-                Ok(data)
-            }
-            Ok(AudioResponse::Error(e)) => {
-                error!("Failed to stop recording: {}", e);
-                Err(RecorderError::AudioError(e))
-            }
-            Ok(_) => {
-                error!("Unexpected response while stopping recording");
-                Err(RecorderError::AudioError("Unexpected response".to_string()))
-            }
-            Err(e) => {
-                error!("Failed to receive stop recording response: {}", e);
-                Err(RecorderError::ReceiveError(e.to_string()))
+pub async fn list_recordings(app: AppHandle) -> Result<Vec<ActiveRecording>> {
+    match request(&app, AudioCommand::ListRecordings).await? {
+        AudioResponse::Recordings(recordings) => Ok(recordings),
+        AudioResponse::Error(e) => Err(e.into()),
+        _ => Err(RecorderError::UnexpectedResponse),
+    }
+}
+
+/// Stops the recording with `recording_id`, whether it runs on the main
+/// session or in the background, and returns its WAV file.
+#[tauri::command]
+pub async fn stop_recording_by_id(app: AppHandle, recording_id: String) -> Result<RecordingFile> {
+    debug!("Stopping recording {}", recording_id);
+    match request_with_timeout(
+        &app,
+        AudioCommand::StopRecordingById(recording_id),
+        SAVE_TIMEOUT,
+    )
+    .await?
+    {
+        AudioResponse::RecordingSaved(file, stats) => {
+            if stats.overruns > 0 {
+                warn!(
+                    "Audio capture for {} overran {} times ({} samples dropped)",
+                    file.recording_id, stats.overruns, stats.dropped_samples
+                );
             }
+            Ok(file)
+        }
+        AudioResponse::InvalidTransition(e) => Err(e.into()),
+        AudioResponse::Error(e) => {
+            error!("Failed to stop recording: {}", e);
+            Err(e.into())
         }
+        _ => Err(RecorderError::UnexpectedResponse),
+    }
+}
+
+/// Opens `device_id` the way `init_recording_session` would, captures for
+/// `seconds` (at most 5) and reports the negotiated config, levels, clipping
+/// and a WAV preview. Devices already open for a session, a background
+/// recording or another test are rejected with `DEVICE_IN_USE`.
+#[tauri::command]
+pub async fn test_recording_device(
+    app: AppHandle,
+    device_id: String,
+    seconds: f32,
+    requested_config: Option<RequestedConfig>,
+) -> Result<DeviceTestReport> {
+    debug!("Testing recording device {}", device_id);
+    // The capture itself takes up to MAX_TEST_SECONDS
+    let timeout = COMMAND_TIMEOUT + Duration::from_secs_f32(seconds.clamp(0.0, MAX_TEST_SECONDS));
+    let (config, audio, stats) = match request_with_timeout(
+        &app,
+        AudioCommand::TestRecordingDevice {
+            device_id: device_id.clone(),
+            seconds,
+            requested_config,
+        },
+        timeout,
+    )
+    .await?
+    {
+        AudioResponse::DeviceTest(config, audio, stats) => Ok((config, audio, stats)),
+        AudioResponse::InvalidTransition(e) => Err(e.into()),
+        AudioResponse::Error(e) => Err(e.into()),
+        _ => Err(RecorderError::UnexpectedResponse),
+    }?;
+
+    let preview_wav =
+        encode_wav_pcm16(&audio).map_err(|e| RecorderError::WavEncodingError(e.to_string()))?;
+    Ok(DeviceTestReport {
+        device_id,
+        config,
+        duration_secs: audio.duration_secs(),
+        peak: peak(&audio.samples),
+        rms: rms(&audio.samples),
+        clipped_samples: stats.clipped_samples,
+        preview_wav: BASE64_STANDARD.encode(preview_wav),
     })
 }
 
+/// Stops the recording and discards its audio without saving it. Streams
+/// still end: a final, empty `recording-audio-chunk` is emitted, and so is
+/// `speech-end` for a segment in progress.
 #[tauri::command]
-pub async fn cancel_recording() -> Result<()> {
+pub async fn cancel_recording(app: AppHandle) -> Result<()> {
     debug!("Canceling recording");
-    with_thread(|tx, rx| {
-        tx.send(AudioCommand::StopRecording)
-            .map_err(|e| RecorderError::SendError(e.to_string()))?;
-
-        match rx.recv() {
-            Ok(AudioResponse::AudioData(_)) => {
-                *IS_RECORDING.lock().unwrap() = false;
-                info!("Recording canceled successfully");
-                Ok(())
-            }
-            Ok(AudioResponse::Error(e)) => {
-                error!("Failed to cancel recording: {}", e);
-                Err(RecorderError::AudioError(e))
-            }
-            Ok(_) => {
-                error!("Unexpected response while canceling recording");
-                Err(RecorderError::AudioError("Unexpected response".to_string()))
-            }
-            Err(e) => {
-                error!("Failed to receive cancel recording response: {}", e);
-                Err(RecorderError::ReceiveError(e.to_string()))
-            }
+    match request(&app, AudioCommand::CancelRecording).await? {
+        AudioResponse::Success(_) => {
+            info!("Recording canceled successfully");
+            Ok(())
         }
-    })
+        AudioResponse::InvalidTransition(e) => Err(e.into()),
+        AudioResponse::Error(e) => {
+            error!("Failed to cancel recording: {}", e);
+            Err(e.into())
+        }
+        _ => {
+            error!("Unexpected response while canceling recording");
+            Err(RecorderError::UnexpectedResponse)
+        }
+    }
+}
+
+/// Deletes the file of a stopped recording once the frontend is done with it.
+/// Files left behind are deleted on startup after a week.
+#[tauri::command]
+pub async fn delete_recording(app: AppHandle, recording_id: String) -> Result<()> {
+    delete_recording_file(&app, &recording_id)
+        .map_err(|e| RecorderError::DeleteError(e.to_string()))
+}
+
+/// Lists recordings recovered after a crash or force-quit interrupted them.
+#[tauri::command]
+pub async fn list_recoverable_recordings(app: AppHandle) -> Result<Vec<RecoverableRecording>> {
+    list_recoverable(&app).map_err(|e| RecorderError::RecoveryError(e.to_string()))
+}
+
+/// Deletes a recovered recording the user has transcribed or dismissed.
+#[tauri::command]
+pub async fn discard_recoverable_recording(app: AppHandle, recording_id: String) -> Result<()> {
+    discard_recoverable(&app, &recording_id)
+        .map_err(|e| RecorderError::RecoveryError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    /// Runs `thread` under a supervisor that swaps `dead` in `slot` for `next`,
+    /// and returns the exit reason along with whether it was swapped.
+    fn supervise_slot(
+        slot: &Arc<Mutex<Option<u32>>>,
+        dead: u32,
+        next: u32,
+        thread: impl FnOnce() + Send + 'static,
+    ) -> (String, bool) {
+        let (exited_tx, exited_rx) = mpsc::channel();
+        let slot = slot.clone();
+        supervise(std::thread::spawn(thread), move |reason| {
+            let replaced = replace_if_current(&slot, |thread| *thread == dead, || Some(next));
+            exited_tx.send((reason, replaced)).unwrap();
+        })
+        .unwrap();
+        exited_rx.recv_timeout(Duration::from_secs(5)).unwrap()
+    }
+
+    #[test]
+    fn panicked_thread_is_replaced() {
+        let slot = Arc::new(Mutex::new(Some(1)));
+        let (reason, replaced) = supervise_slot(&slot, 1, 2, || panic!("device exploded"));
+        assert_eq!(reason, "device exploded");
+        assert!(replaced);
+        assert_eq!(*slot.lock().unwrap(), Some(2));
+
+        let (reason, replaced) = supervise_slot(&slot, 2, 3, || panic!("{} samples", 42));
+        assert_eq!(reason, "42 samples");
+        assert!(replaced);
+        assert_eq!(*slot.lock().unwrap(), Some(3));
+    }
+
+    #[test]
+    fn closed_thread_is_not_replaced() {
+        // close_thread and restart_audio_thread take the thread out of the slot
+        let slot = Arc::new(Mutex::new(None));
+        let (reason, replaced) = supervise_slot(&slot, 1, 2, || {});
+        assert_eq!(reason, "The audio thread exited unexpectedly");
+        assert!(!replaced);
+        assert_eq!(*slot.lock().unwrap(), None);
+    }
+
+    #[test]
+    fn restarted_thread_is_not_replaced() {
+        // restart_audio_thread already put a new thread in the slot
+        let slot = Arc::new(Mutex::new(Some(5)));
+        let (_, replaced) = supervise_slot(&slot, 1, 2, || panic!("hung"));
+        assert!(!replaced);
+        assert_eq!(*slot.lock().unwrap(), Some(5));
+    }
+
+    #[test]
+    fn failed_respawn_empties_the_slot() {
+        let slot = Mutex::new(Some(1));
+        assert!(replace_if_current(&slot, |thread| *thread == 1, || None));
+        assert_eq!(*slot.lock().unwrap(), None);
+    }
 }
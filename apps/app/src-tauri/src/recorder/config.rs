@@ -0,0 +1,278 @@
+use super::error::AudioError;
+use cpal::traits::DeviceTrait;
+use cpal::{Device, SampleFormat, SampleRate, SupportedStreamConfig, SupportedStreamConfigRange};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+/// Sample rate the voice heuristic opens devices at when they support it.
+const VOICE_SAMPLE_RATE: u32 = 16_000;
+
+/// Capture parameters the user explicitly asked for. Unset fields are left to
+/// the voice heuristic.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestedConfig {
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+    /// Zero-based device channel to record, e.g. the one input of an
+    /// interface a mic is plugged into. Unset averages every channel.
+    pub input_channel: Option<u16>,
+}
+
+/// The config an input stream was actually opened with.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureConfig {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub sample_format: String,
+    /// The only channel recorded, when one was requested
+    pub input_channel: Option<u16>,
+}
+
+impl From<&SupportedStreamConfig> for CaptureConfig {
+    fn from(config: &SupportedStreamConfig) -> Self {
+        Self {
+            sample_rate: config.sample_rate().0,
+            channels: config.channels(),
+            sample_format: config.sample_format().to_string(),
+            input_channel: None,
+        }
+    }
+}
+
+/// One entry of a device's `supported_input_configs`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigRange {
+    pub sample_format: String,
+    pub channels: u16,
+    pub min_sample_rate: u32,
+    pub max_sample_rate: u32,
+}
+
+impl From<&SupportedStreamConfigRange> for ConfigRange {
+    fn from(range: &SupportedStreamConfigRange) -> Self {
+        Self {
+            sample_format: range.sample_format().to_string(),
+            channels: range.channels(),
+            min_sample_rate: range.min_sample_rate().0,
+            max_sample_rate: range.max_sample_rate().0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceCapabilities {
+    pub device_id: String,
+    pub configs: Vec<ConfigRange>,
+    pub default_config: Option<CaptureConfig>,
+}
+
+/// Outcome of a short test capture on a device.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceTestReport {
+    pub device_id: String,
+    pub config: CaptureConfig,
+    pub duration_secs: f32,
+    /// Levels of the 16 kHz preview, from 0.0 to 1.0
+    pub peak: f32,
+    pub rms: f32,
+    /// Raw device samples that hit full scale
+    pub clipped_samples: u64,
+    /// The captured audio as a base64-encoded 16-bit 16 kHz mono WAV file,
+    /// ready for a `data:audio/wav;base64,` URL
+    pub preview_wav: String,
+}
+
+pub fn device_capabilities(
+    device: &Device,
+    device_id: String,
+) -> Result<DeviceCapabilities, AudioError> {
+    let configs = device
+        .supported_input_configs()?
+        .map(|range| ConfigRange::from(&range))
+        .collect();
+    let default_config = device
+        .default_input_config()
+        .ok()
+        .map(|config| CaptureConfig::from(&config));
+    Ok(DeviceCapabilities {
+        device_id,
+        configs,
+        default_config,
+    })
+}
+
+/// Finds a supported config matching every field of `requested`. Without a
+/// requested rate, the voice rate is preferred, then the device's default.
+fn find_requested_config(
+    ranges: &[SupportedStreamConfigRange],
+    requested: &RequestedConfig,
+    default_sample_rate: Option<u32>,
+) -> Option<SupportedStreamConfig> {
+    let matching: Vec<_> = ranges
+        .iter()
+        .copied()
+        .filter(|range| requested.channels.is_none_or(|c| range.channels() == c))
+        .filter(|range| requested.input_channel.is_none_or(|c| range.channels() > c))
+        .collect();
+    let rates = match requested.sample_rate {
+        Some(rate) => vec![rate],
+        None => [Some(VOICE_SAMPLE_RATE), default_sample_rate]
+            .into_iter()
+            .flatten()
+            .collect(),
+    };
+    rates
+        .into_iter()
+        .find_map(|rate| {
+            matching
+                .iter()
+                .find_map(|range| range.try_with_sample_rate(SampleRate(rate)))
+        })
+        .or_else(|| {
+            // Neither rate is supported: get as close to the voice rate as we can
+            requested.sample_rate.is_none().then(|| {
+                matching
+                    .iter()
+                    .min_by_key(|range| range.min_sample_rate().0)
+                    .map(|range| {
+                        let rate = VOICE_SAMPLE_RATE
+                            .clamp(range.min_sample_rate().0, range.max_sample_rate().0);
+                        range.with_sample_rate(SampleRate(rate))
+                    })
+            })?
+        })
+}
+
+/// Picks an input config for `device`, honoring `requested` when the device
+/// supports it and otherwise falling back to a voice-friendly config.
+pub fn select_input_config(
+    device: &Device,
+    requested: Option<&RequestedConfig>,
+) -> Result<SupportedStreamConfig, AudioError> {
+    let ranges: Vec<_> = device.supported_input_configs()?.collect();
+
+    if let Some(requested) = requested {
+        let default_sample_rate = device
+            .default_input_config()
+            .ok()
+            .map(|config| config.sample_rate().0);
+        match find_requested_config(&ranges, requested, default_sample_rate) {
+            Some(config) => {
+                info!(
+                    "Using requested config: {} Hz, {} channels",
+                    config.sample_rate().0,
+                    config.channels()
+                );
+                return Ok(config);
+            }
+            None => warn!(
+                "Requested config {:?} is not supported, falling back to a voice config",
+                requested
+            ),
+        }
+    }
+
+    // Config can sometimes be overkill (96kHz with 3 channels)
+    // Attempt to create config with lower voice-friendly parameters, otherwise use default config
+    let is_voice_friendly = |config: &SupportedStreamConfigRange| {
+        let min_rate = config.min_sample_rate().0;
+        let max_rate = config.max_sample_rate().0;
+        let channels = config.channels();
+
+        // Look for mono or stereo config that supports common voice sample rates
+        (channels == 1 || channels == 2) && (min_rate..=max_rate).contains(&VOICE_SAMPLE_RATE)
+    };
+    // Every format is converted to f32 on capture, but prefer it when offered
+    let voice_config = ranges
+        .iter()
+        .copied()
+        .filter(is_voice_friendly)
+        .min_by_key(|config| config.sample_format() != SampleFormat::F32);
+
+    match voice_config {
+        Some(config) => {
+            // Use 16kHz for voice and prefer mono if available
+            let config = config.with_sample_rate(SampleRate(VOICE_SAMPLE_RATE));
+            info!(
+                "Using voice-optimized config: {} Hz, {} channels",
+                config.sample_rate().0,
+                config.channels()
+            );
+            Ok(config)
+        }
+        None => {
+            // If no voice-friendly config found, try to get default and optimize it
+            let default_config = device.default_input_config()?;
+            info!(
+                "No voice-optimized config found. Default config: {} Hz, {} channels",
+                default_config.sample_rate().0,
+                default_config.channels()
+            );
+            Ok(default_config)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cpal::SupportedBufferSize;
+
+    fn range(channels: u16, min_rate: u32, max_rate: u32) -> SupportedStreamConfigRange {
+        SupportedStreamConfigRange::new(
+            channels,
+            SampleRate(min_rate),
+            SampleRate(max_rate),
+            SupportedBufferSize::Unknown,
+            SampleFormat::F32,
+        )
+    }
+
+    fn requested(channels: Option<u16>, input_channel: Option<u16>) -> RequestedConfig {
+        RequestedConfig {
+            sample_rate: None,
+            channels,
+            input_channel,
+        }
+    }
+
+    #[test]
+    fn channels_only_request_uses_the_voice_rate() {
+        let ranges = [range(2, 8_000, 192_000)];
+        let config = find_requested_config(&ranges, &requested(Some(2), None), Some(48_000));
+        assert_eq!(config.unwrap().sample_rate().0, VOICE_SAMPLE_RATE);
+    }
+
+    #[test]
+    fn channels_only_request_falls_back_to_the_default_rate() {
+        let ranges = [range(2, 44_100, 192_000)];
+        let config = find_requested_config(&ranges, &requested(Some(2), None), Some(48_000));
+        assert_eq!(config.unwrap().sample_rate().0, 48_000);
+
+        let config = find_requested_config(&ranges, &requested(Some(2), None), None);
+        assert_eq!(config.unwrap().sample_rate().0, 44_100);
+    }
+
+    #[test]
+    fn input_channel_needs_enough_channels() {
+        let ranges = [range(1, 16_000, 48_000), range(4, 16_000, 48_000)];
+        let config = find_requested_config(&ranges, &requested(None, Some(2)), None);
+        assert_eq!(config.unwrap().channels(), 4);
+
+        let config = find_requested_config(&ranges, &requested(Some(1), Some(2)), None);
+        assert!(config.is_none());
+    }
+
+    #[test]
+    fn unsupported_requested_rate_is_not_replaced() {
+        let ranges = [range(1, 44_100, 48_000)];
+        let mut request = requested(None, None);
+        request.sample_rate = Some(16_000);
+        assert!(find_requested_config(&ranges, &request, Some(48_000)).is_none());
+    }
+}
@@ -0,0 +1,257 @@
+use rubato::{FftFixedInOut, Resampler, ResamplerConstructionError};
+use tracing::error;
+
+/// Sample rate every transcription backend expects.
+pub const TARGET_SAMPLE_RATE: u32 = 16_000;
+/// Channel count every transcription backend expects.
+pub const TARGET_CHANNELS: u16 = 1;
+
+/// Input the resampler works on at a time. Rubato rounds it up when the
+/// rates don't divide evenly, e.g. to 20ms at 44.1kHz.
+const RESAMPLE_CHUNK_MS: usize = 10;
+
+/// Streaming downmix + resample stage that turns whatever interleaved f32
+/// frames the device delivers into 16 kHz mono, either by averaging the
+/// channels or by keeping just one of them.
+///
+/// State is carried across calls, so packets of any size can be fed in as
+/// they arrive from the cpal callback. Resampling goes through a band-limited
+/// FFT resampler, so content above 8 kHz is filtered out instead of folding
+/// back into the speech band.
+pub struct AudioConverter {
+    channels: usize,
+    /// Channel kept instead of averaging, so a single mic on a multi-input
+    /// interface isn't attenuated by the silent inputs
+    input_channel: Option<usize>,
+    input_sample_rate: u32,
+    /// Unset when the input is already 16 kHz
+    resampler: Option<FftFixedInOut<f32>>,
+    /// Downmixed input waiting for a whole resampler chunk
+    pending: Vec<f32>,
+    resampled: Vec<Vec<f32>>,
+    /// Output samples still to drop to make up for the resampler's delay
+    delay: usize,
+    /// Input frames taken and output samples produced since the last reset
+    frames_in: u64,
+    samples_out: u64,
+}
+
+impl AudioConverter {
+    /// Averages every input channel unless `input_channel` is given. An
+    /// `input_channel` the device doesn't have is ignored.
+    pub fn new(
+        input_sample_rate: u32,
+        input_channels: u16,
+        input_channel: Option<u16>,
+    ) -> Result<Self, ResamplerConstructionError> {
+        let channels = input_channels.max(1) as usize;
+        let resampler = if input_sample_rate == TARGET_SAMPLE_RATE {
+            None
+        } else {
+            Some(FftFixedInOut::new(
+                input_sample_rate as usize,
+                TARGET_SAMPLE_RATE as usize,
+                (input_sample_rate as usize * RESAMPLE_CHUNK_MS / 1000).max(1),
+                1,
+            )?)
+        };
+        let resampled = resampler
+            .as_ref()
+            .map(|resampler| resampler.output_buffer_allocate(true))
+            .unwrap_or_default();
+        let delay = resampler
+            .as_ref()
+            .map_or(0, |resampler| resampler.output_delay());
+        Ok(Self {
+            channels,
+            input_channel: input_channel
+                .map(usize::from)
+                .filter(|&channel| channel < channels),
+            input_sample_rate,
+            resampler,
+            pending: Vec::new(),
+            resampled,
+            delay,
+            frames_in: 0,
+            samples_out: 0,
+        })
+    }
+
+    /// Converts a packet of interleaved input samples, appending the 16 kHz
+    /// mono result to `output`. Trailing partial frames are ignored. Up to one
+    /// resampler chunk is held back until more input arrives or [`flush`] is
+    /// called.
+    ///
+    /// [`flush`]: AudioConverter::flush
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        let channels = self.channels;
+        let input_channel = self.input_channel;
+        let mono = input
+            .chunks_exact(channels)
+            .map(|frame| match input_channel {
+                Some(channel) => frame[channel],
+                None => frame.iter().sum::<f32>() / channels as f32,
+            });
+
+        if self.resampler.is_none() {
+            output.extend(mono);
+            return;
+        }
+        let len = self.pending.len();
+        self.pending.extend(mono);
+        self.frames_in += (self.pending.len() - len) as u64;
+        self.resample_pending(output);
+    }
+
+    /// Appends everything still held back by the resampler to `output`, then
+    /// resets it so the next recording starts clean.
+    pub fn flush(&mut self, output: &mut Vec<f32>) {
+        let Some(chunk) = self
+            .resampler
+            .as_ref()
+            .map(|resampler| resampler.input_frames_next())
+        else {
+            return;
+        };
+        let expected = self.frames_in * TARGET_SAMPLE_RATE as u64 / self.input_sample_rate as u64;
+        let start = output.len();
+        // Pad with silence to push the last real samples through the filter
+        while self.samples_out < expected {
+            let padded = self.pending.len().next_multiple_of(chunk).max(chunk);
+            self.pending.resize(padded, 0.0);
+            self.resample_pending(output);
+        }
+        let excess = (self.samples_out - expected) as usize;
+        output.truncate(output.len().saturating_sub(excess).max(start));
+        self.reset();
+    }
+
+    /// Drops any audio held back by the resampler.
+    pub fn reset(&mut self) {
+        if let Some(resampler) = self.resampler.as_mut() {
+            resampler.reset();
+            self.delay = resampler.output_delay();
+        }
+        self.pending.clear();
+        self.frames_in = 0;
+        self.samples_out = 0;
+    }
+
+    fn resample_pending(&mut self, output: &mut Vec<f32>) {
+        let Some(resampler) = self.resampler.as_mut() else {
+            return;
+        };
+        let chunk = resampler.input_frames_next();
+        let mut consumed = 0;
+        while self.pending.len() - consumed >= chunk {
+            let input = [&self.pending[consumed..consumed + chunk]];
+            let produced = match resampler.process_into_buffer(&input, &mut self.resampled, None) {
+                Ok((_, produced)) => produced,
+                Err(e) => {
+                    error!("Failed to resample audio: {}", e);
+                    break;
+                }
+            };
+            consumed += chunk;
+
+            let resampled = &self.resampled[0][..produced];
+            let skip = self.delay.min(resampled.len());
+            self.delay -= skip;
+            output.extend_from_slice(&resampled[skip..]);
+            self.samples_out += (resampled.len() - skip) as u64;
+        }
+        self.pending.drain(..consumed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recorder::level::rms;
+    use std::f32::consts::{FRAC_1_SQRT_2, TAU};
+
+    fn sine(frequency: f32, sample_rate: u32, seconds: f32) -> Vec<f32> {
+        (0..(sample_rate as f32 * seconds) as usize)
+            .map(|i| (TAU * frequency * i as f32 / sample_rate as f32).sin())
+            .collect()
+    }
+
+    /// Converts `input` in uneven packets, the way the cpal callback delivers it.
+    fn convert(converter: &mut AudioConverter, input: &[f32]) -> Vec<f32> {
+        let mut output = Vec::new();
+        for packet in input.chunks(511 * converter.channels) {
+            converter.process(packet, &mut output);
+        }
+        converter.flush(&mut output);
+        output
+    }
+
+    #[test]
+    fn output_length_matches_the_input_duration() {
+        for rate in [8_000, 16_000, 22_050, 44_100, 48_000, 96_000] {
+            let mut converter = AudioConverter::new(rate, 1, None).unwrap();
+            let output = convert(&mut converter, &vec![0.0; rate as usize]);
+            assert_eq!(output.len(), TARGET_SAMPLE_RATE as usize, "{} Hz", rate);
+        }
+    }
+
+    #[test]
+    fn converter_can_be_reused_after_a_flush() {
+        let mut converter = AudioConverter::new(48_000, 1, None).unwrap();
+        for _ in 0..2 {
+            let output = convert(&mut converter, &vec![0.0; 24_000]);
+            assert_eq!(output.len(), 8_000);
+        }
+    }
+
+    #[test]
+    fn stereo_is_averaged_to_mono() {
+        let input: Vec<f32> = [0.8, 0.2].repeat(1_000);
+        let mut converter = AudioConverter::new(TARGET_SAMPLE_RATE, 2, None).unwrap();
+        let output = convert(&mut converter, &input);
+        assert_eq!(output.len(), 1_000);
+        assert!(output.iter().all(|sample| (sample - 0.5).abs() < 1e-6));
+    }
+
+    #[test]
+    fn input_channel_is_kept_without_averaging() {
+        let input: Vec<f32> = [0.0, 0.6, 0.0, 0.0].repeat(1_000);
+        let mut converter = AudioConverter::new(TARGET_SAMPLE_RATE, 4, Some(1)).unwrap();
+        let output = convert(&mut converter, &input);
+        assert_eq!(output.len(), 1_000);
+        assert!(output.iter().all(|&sample| sample == 0.6));
+    }
+
+    #[test]
+    fn missing_input_channel_falls_back_to_averaging() {
+        let mut converter = AudioConverter::new(TARGET_SAMPLE_RATE, 2, Some(2)).unwrap();
+        let mut output = Vec::new();
+        converter.process(&[0.8, 0.2], &mut output);
+        assert_eq!(output, vec![0.5]);
+    }
+
+    #[test]
+    fn trailing_partial_frames_are_ignored() {
+        let mut converter = AudioConverter::new(TARGET_SAMPLE_RATE, 2, None).unwrap();
+        let mut output = Vec::new();
+        converter.process(&[0.5, 0.5, 0.5], &mut output);
+        assert_eq!(output, vec![0.5]);
+    }
+
+    #[test]
+    fn speech_band_passes_through_resampling() {
+        let mut converter = AudioConverter::new(48_000, 1, None).unwrap();
+        let output = convert(&mut converter, &sine(1_000.0, 48_000, 1.0));
+        let level = rms(&output[1_600..14_400]);
+        assert!((level - FRAC_1_SQRT_2).abs() < 0.02, "{}", level);
+    }
+
+    #[test]
+    fn content_above_8khz_does_not_alias() {
+        // At 48 kHz, 12 kHz would fold down to 4 kHz without a proper filter
+        let mut converter = AudioConverter::new(48_000, 1, None).unwrap();
+        let output = convert(&mut converter, &sine(12_000.0, 48_000, 1.0));
+        let level = rms(&output[1_600..14_400]);
+        assert!(level < 0.01, "aliased at {}", level);
+    }
+}
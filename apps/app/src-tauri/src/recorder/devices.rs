@@ -0,0 +1,178 @@
+use super::error::AudioError;
+use super::events::emit;
+use cpal::traits::{DeviceTrait, HostTrait};
+use cpal::{Device, Host};
+use serde::Serialize;
+use std::time::Duration;
+use tauri::AppHandle;
+use tracing::{debug, warn};
+
+/// Pseudo device that always resolves to the current OS default input.
+pub const DEFAULT_DEVICE_ID: &str = "default";
+
+/// Emitted with the new device list whenever microphones are added, removed or
+/// the OS default input changes.
+pub const DEVICES_CHANGED_EVENT: &str = "devices-changed";
+
+const DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceInfo {
+    pub device_id: String,
+    pub label: String,
+    pub is_default: bool,
+}
+
+/// Builds a stable identifier from the host, the device name and its position
+/// among devices sharing that name, so identical headsets don't collide.
+fn device_id(host_name: &str, name: &str, index: usize) -> String {
+    format!("{}:{}#{}", host_name, name, index)
+}
+
+/// Stable IDs for devices listed with `names`, in the same order.
+fn device_ids(host_name: &str, names: &[String]) -> Vec<String> {
+    names
+        .iter()
+        .enumerate()
+        .map(|(position, name)| {
+            let index = names[..position].iter().filter(|n| *n == name).count();
+            device_id(host_name, name, index)
+        })
+        .collect()
+}
+
+/// Position of `device_id` among devices with the given IDs and names.
+fn resolve_device_id(ids: &[String], names: &[String], device_id: &str) -> Option<usize> {
+    ids.iter()
+        .position(|id| id == device_id)
+        // Settings saved before stable IDs hold the bare device name
+        .or_else(|| names.iter().position(|name| name == device_id))
+}
+
+/// All named input devices on `host`, paired with their stable IDs.
+fn named_input_devices(host: &Host) -> Result<Vec<(String, String, Device)>, AudioError> {
+    let (names, devices): (Vec<String>, Vec<Device>) = host
+        .input_devices()?
+        .filter_map(|device| Some((device.name().ok()?, device)))
+        .unzip();
+    let ids = device_ids(host.id().name(), &names);
+    Ok(ids
+        .into_iter()
+        .zip(names)
+        .zip(devices)
+        .map(|((id, name), device)| (id, name, device))
+        .collect())
+}
+
+/// Lists input devices, starting with the "default" pseudo-entry.
+pub fn list_input_devices(host: &Host) -> Result<Vec<DeviceInfo>, AudioError> {
+    let default_name = host.default_input_device().and_then(|d| d.name().ok());
+    let devices = named_input_devices(host)?;
+
+    let mut infos = Vec::with_capacity(devices.len() + 1);
+    if let Some(default_name) = &default_name {
+        infos.push(DeviceInfo {
+            device_id: DEFAULT_DEVICE_ID.to_string(),
+            label: format!("Default ({})", default_name),
+            is_default: true,
+        });
+    }
+    infos.extend(devices.into_iter().map(|(device_id, label, _)| DeviceInfo {
+        device_id,
+        label,
+        is_default: false,
+    }));
+    Ok(infos)
+}
+
+/// Resolves a device ID from [`list_input_devices`] back to a cpal device.
+pub fn find_input_device(host: &Host, device_id: &str) -> Result<Device, AudioError> {
+    if device_id == DEFAULT_DEVICE_ID {
+        return host
+            .default_input_device()
+            .ok_or_else(|| AudioError::DeviceNotFound(device_id.to_string()));
+    }
+
+    let mut devices = named_input_devices(host)?;
+    let (ids, names): (Vec<String>, Vec<String>) = devices
+        .iter()
+        .map(|(id, name, _)| (id.clone(), name.clone()))
+        .unzip();
+
+    match resolve_device_id(&ids, &names, device_id) {
+        Some(position) => Ok(devices.swap_remove(position).2),
+        None => Err(AudioError::DeviceNotFound(device_id.to_string())),
+    }
+}
+
+/// Polls the input device list in the background and emits
+/// [`DEVICES_CHANGED_EVENT`] whenever it changes.
+pub fn spawn_device_watcher(app: AppHandle) -> std::io::Result<()> {
+    std::thread::Builder::new()
+        .name("audio-device-watcher".to_string())
+        .spawn(move || {
+            let host = cpal::default_host();
+            let mut known = list_input_devices(&host).unwrap_or_default();
+            loop {
+                std::thread::sleep(DEVICE_POLL_INTERVAL);
+                match list_input_devices(&host) {
+                    Ok(devices) if devices != known => {
+                        debug!("Input devices changed ({} devices)", devices.len());
+                        emit(&app, DEVICES_CHANGED_EVENT, devices.clone());
+                        known = devices;
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("Failed to poll input devices: {}", e),
+                }
+            }
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn ids_include_the_host_name_and_index() {
+        assert_eq!(device_id("ALSA", "USB Mic", 0), "ALSA:USB Mic#0");
+        assert_eq!(device_id("CoreAudio", "a:b#1", 2), "CoreAudio:a:b#1#2");
+    }
+
+    #[test]
+    fn identical_names_are_numbered_in_order() {
+        let names = names(&["Headset", "Built-in", "Headset", "Headset"]);
+        assert_eq!(
+            device_ids("WASAPI", &names),
+            [
+                "WASAPI:Headset#0",
+                "WASAPI:Built-in#0",
+                "WASAPI:Headset#1",
+                "WASAPI:Headset#2",
+            ]
+        );
+    }
+
+    #[test]
+    fn stable_ids_resolve_to_their_device() {
+        let names = names(&["Headset", "Headset"]);
+        let ids = device_ids("ALSA", &names);
+        assert_eq!(resolve_device_id(&ids, &names, "ALSA:Headset#1"), Some(1));
+        assert_eq!(resolve_device_id(&ids, &names, "ALSA:Headset#2"), None);
+        // From another host
+        assert_eq!(resolve_device_id(&ids, &names, "JACK:Headset#0"), None);
+    }
+
+    #[test]
+    fn bare_names_from_old_settings_still_resolve() {
+        let names = names(&["Built-in", "Headset", "Headset"]);
+        let ids = device_ids("ALSA", &names);
+        assert_eq!(resolve_device_id(&ids, &names, "Headset"), Some(1));
+        assert_eq!(resolve_device_id(&ids, &names, "Missing"), None);
+    }
+}
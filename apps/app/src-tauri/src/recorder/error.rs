@@ -0,0 +1,217 @@
+use super::state::InvalidTransition;
+use cpal::{
+    BackendSpecificError, BuildStreamError, DefaultStreamConfigError, DevicesError,
+    PlayStreamError, SupportedStreamConfigsError,
+};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use thiserror::Error;
+
+/// Failures reported by the audio thread.
+#[derive(Debug, Clone, Error)]
+pub enum AudioError {
+    #[error("Audio host is unavailable: {0}")]
+    HostUnavailable(String),
+    #[error("Recording device not found: {0}")]
+    DeviceNotFound(String),
+    #[error("Microphone permission denied: {0}")]
+    PermissionDenied(String),
+    #[error("Recording device is unavailable: {0}")]
+    DeviceUnavailable(String),
+    #[error("Unsupported recording config: {0}")]
+    UnsupportedConfig(String),
+    #[error("Failed to build stream: {0}")]
+    StreamBuildFailed(String),
+    #[error("Failed to start stream: {0}")]
+    StreamStartFailed(String),
+    #[error("Failed to start capture thread: {0}")]
+    CaptureThreadFailed(String),
+    #[error("Failed to save recording: {0}")]
+    RecordingFileFailed(String),
+}
+
+impl AudioError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AudioError::HostUnavailable(_) => "HOST_UNAVAILABLE",
+            AudioError::DeviceNotFound(_) => "DEVICE_NOT_FOUND",
+            AudioError::PermissionDenied(_) => "PERMISSION_DENIED",
+            AudioError::DeviceUnavailable(_) => "DEVICE_UNAVAILABLE",
+            AudioError::UnsupportedConfig(_) => "UNSUPPORTED_CONFIG",
+            AudioError::StreamBuildFailed(_) => "STREAM_BUILD_FAILED",
+            AudioError::StreamStartFailed(_) => "STREAM_START_FAILED",
+            AudioError::CaptureThreadFailed(_) => "CAPTURE_THREAD_FAILED",
+            AudioError::RecordingFileFailed(_) => "RECORDING_FILE_FAILED",
+        }
+    }
+
+    /// cpal has no dedicated permission error, so denied microphone access
+    /// surfaces as a backend error. Pick it out by its description.
+    fn from_backend(err: BackendSpecificError, fallback: fn(String) -> AudioError) -> Self {
+        let description = err.description;
+        let lowercase = description.to_lowercase();
+        // Not "access": errors like "failed to access device" aren't about permissions
+        if ["permission", "denied", "not authorized"]
+            .iter()
+            .any(|keyword| lowercase.contains(keyword))
+        {
+            AudioError::PermissionDenied(description)
+        } else {
+            fallback(description)
+        }
+    }
+}
+
+impl From<DevicesError> for AudioError {
+    fn from(e: DevicesError) -> Self {
+        match e {
+            DevicesError::BackendSpecific { err } => {
+                AudioError::from_backend(err, AudioError::HostUnavailable)
+            }
+        }
+    }
+}
+
+impl From<SupportedStreamConfigsError> for AudioError {
+    fn from(e: SupportedStreamConfigsError) -> Self {
+        match e {
+            SupportedStreamConfigsError::DeviceNotAvailable => {
+                AudioError::DeviceUnavailable(e.to_string())
+            }
+            SupportedStreamConfigsError::InvalidArgument => {
+                AudioError::UnsupportedConfig(e.to_string())
+            }
+            SupportedStreamConfigsError::BackendSpecific { err } => {
+                AudioError::from_backend(err, AudioError::UnsupportedConfig)
+            }
+        }
+    }
+}
+
+impl From<DefaultStreamConfigError> for AudioError {
+    fn from(e: DefaultStreamConfigError) -> Self {
+        match e {
+            DefaultStreamConfigError::DeviceNotAvailable => {
+                AudioError::DeviceUnavailable(e.to_string())
+            }
+            DefaultStreamConfigError::StreamTypeNotSupported => {
+                AudioError::UnsupportedConfig(e.to_string())
+            }
+            DefaultStreamConfigError::BackendSpecific { err } => {
+                AudioError::from_backend(err, AudioError::UnsupportedConfig)
+            }
+        }
+    }
+}
+
+impl From<BuildStreamError> for AudioError {
+    fn from(e: BuildStreamError) -> Self {
+        match e {
+            BuildStreamError::DeviceNotAvailable => AudioError::DeviceUnavailable(e.to_string()),
+            BuildStreamError::StreamConfigNotSupported | BuildStreamError::InvalidArgument => {
+                AudioError::UnsupportedConfig(e.to_string())
+            }
+            BuildStreamError::StreamIdOverflow => AudioError::StreamBuildFailed(e.to_string()),
+            BuildStreamError::BackendSpecific { err } => {
+                AudioError::from_backend(err, AudioError::StreamBuildFailed)
+            }
+        }
+    }
+}
+
+impl From<PlayStreamError> for AudioError {
+    fn from(e: PlayStreamError) -> Self {
+        match e {
+            PlayStreamError::DeviceNotAvailable => AudioError::DeviceUnavailable(e.to_string()),
+            PlayStreamError::BackendSpecific { err } => {
+                AudioError::from_backend(err, AudioError::StreamStartFailed)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RecorderError {
+    #[error("Failed to send command: {0}")]
+    SendError(String),
+    #[error("Failed to receive response: {0}")]
+    ReceiveError(String),
+    #[error("The audio thread did not respond within {0:?}")]
+    Timeout(std::time::Duration),
+    #[error("Unexpected response from the audio thread")]
+    UnexpectedResponse,
+    #[error("Failed to acquire lock: {0}")]
+    LockError(String),
+    #[error("Failed to encode WAV: {0}")]
+    WavEncodingError(String),
+    #[error("Failed to access recovered recordings: {0}")]
+    RecoveryError(String),
+    #[error("Failed to delete recording: {0}")]
+    DeleteError(String),
+    #[error(transparent)]
+    Audio(#[from] AudioError),
+    #[error(transparent)]
+    InvalidTransition(#[from] InvalidTransition),
+}
+
+impl RecorderError {
+    /// Machine-readable code the frontend can match on.
+    pub fn code(&self) -> &'static str {
+        match self {
+            RecorderError::SendError(_) => "SEND_FAILED",
+            RecorderError::ReceiveError(_) => "RECEIVE_FAILED",
+            RecorderError::Timeout(_) => "AUDIO_THREAD_TIMEOUT",
+            RecorderError::UnexpectedResponse => "UNEXPECTED_RESPONSE",
+            RecorderError::LockError(_) => "LOCK_FAILED",
+            RecorderError::WavEncodingError(_) => "WAV_ENCODING_FAILED",
+            RecorderError::RecoveryError(_) => "RECOVERY_FAILED",
+            RecorderError::DeleteError(_) => "DELETE_FAILED",
+            RecorderError::Audio(e) => e.code(),
+            RecorderError::InvalidTransition(e) => e.code(),
+        }
+    }
+}
+
+/// Serialized as `{ "code": "DEVICE_NOT_FOUND", "message": "..." }`.
+impl Serialize for RecorderError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("RecorderError", 2)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.end()
+    }
+}
+
+pub type Result<T> = std::result::Result<T, RecorderError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backend_error(description: &str) -> AudioError {
+        BuildStreamError::BackendSpecific {
+            err: BackendSpecificError {
+                description: description.to_string(),
+            },
+        }
+        .into()
+    }
+
+    #[test]
+    fn permission_errors_are_picked_out() {
+        for description in [
+            "Permission denied",
+            "Access denied by the system",
+            "The app is not authorized to use the microphone",
+        ] {
+            assert_eq!(backend_error(description).code(), "PERMISSION_DENIED");
+        }
+    }
+
+    #[test]
+    fn other_access_failures_keep_their_code() {
+        assert_eq!(
+            backend_error("Failed to access device").code(),
+            "STREAM_BUILD_FAILED"
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tracing::warn;
+
+/// Emitted with fixed-size 16 kHz mono PCM chunks while a streaming recording is running.
+pub const AUDIO_CHUNK_EVENT: &str = "recording-audio-chunk";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioChunk {
+    /// Position of this chunk within the recording, starting at 0
+    pub sequence: u64,
+    pub sample_rate: u32,
+    pub samples: Vec<f32>,
+    /// Set on the last chunk of a recording, which may be shorter than the others
+    pub is_final: bool,
+}
+
+/// Emitted when the input stream fails mid-session, e.g. a Bluetooth mic disconnecting.
+pub const DEVICE_LOST_EVENT: &str = "device-lost";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceLost {
+    pub device_id: String,
+    /// The recording that was in progress on the device, if any
+    pub recording_id: Option<String>,
+    pub message: String,
+    /// Seconds of audio captured before the failure, which are kept
+    pub kept_seconds: f32,
+    /// Set when capture continued on the system default input
+    pub failed_over_to: Option<String>,
+}
+
+/// Emitted when the native VAD confirms the start of a speech segment.
+pub const SPEECH_START_EVENT: &str = "speech-start";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpeechStart {
+    /// Position of this segment within the recording, starting at 0
+    pub segment: u64,
+    /// Seconds since recording started, including the pre-roll
+    pub start_secs: f32,
+}
+
+/// Emitted with the 16 kHz mono audio of a speech segment once it has ended.
+pub const SPEECH_END_EVENT: &str = "speech-end";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpeechEnd {
+    pub segment: u64,
+    pub start_secs: f32,
+    pub sample_rate: u32,
+    pub samples: Vec<f32>,
+}
+
+/// Emitted when a recording stops capturing on its own, after prolonged
+/// silence or on reaching a session limit. The recording stays open until
+/// `stop_recording` collects the audio.
+pub const RECORDING_AUTO_STOPPED_EVENT: &str = "recording-auto-stopped";
+
+/// Serialized as `{ "recordingId": "...", "reason": "silence", "silenceSecs": 3.0 }`
+/// and so on.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoStopped {
+    pub recording_id: String,
+    #[serde(flatten)]
+    pub reason: RecordingAutoStopped,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(tag = "reason", rename_all = "camelCase")]
+pub enum RecordingAutoStopped {
+    #[serde(rename_all = "camelCase")]
+    Silence { silence_secs: f32 },
+    #[serde(rename_all = "camelCase")]
+    DurationLimit { max_duration_secs: f32 },
+    #[serde(rename_all = "camelCase")]
+    MemoryLimit { max_bytes: u64 },
+}
+
+/// Emitted roughly every 50ms while capturing with the level of the raw input.
+pub const INPUT_LEVEL_EVENT: &str = "input-level";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputLevel {
+    /// The recording being captured, unset while testing a device
+    pub recording_id: Option<String>,
+    /// RMS level over the window, from 0.0 to 1.0
+    pub rms: f32,
+    /// Largest absolute sample in the window, from 0.0 to 1.0
+    pub peak: f32,
+}
+
+/// Emitted when the audio thread died and was replaced. Any session or
+/// recording in progress on it is gone.
+pub const RECORDER_RESET_EVENT: &str = "recorder-reset";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecorderReset {
+    /// Why the old thread died, e.g. its panic message
+    pub reason: String,
+}
+
+/// Emits an event to every webview, logging instead of failing if it can't be delivered.
+pub fn emit<S: Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    if let Err(e) = app.emit(event, payload) {
+        warn!("Failed to emit {} event: {}", event, e);
+    }
+}
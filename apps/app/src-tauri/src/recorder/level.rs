@@ -0,0 +1,20 @@
+/// Samples at or above this absolute level are counted as clipped.
+const CLIPPING_LEVEL: f32 = 0.999;
+
+/// Root mean square level of `samples`, from 0.0 to 1.0 for in-range audio.
+pub fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+/// Largest absolute sample value in `samples`.
+pub fn peak(samples: &[f32]) -> f32 {
+    samples.iter().fold(0.0, |peak, s| peak.max(s.abs()))
+}
+
+/// Number of samples in `samples` that hit full scale.
+pub fn count_clipped(samples: &[f32]) -> usize {
+    samples.iter().filter(|s| s.abs() >= CLIPPING_LEVEL).count()
+}
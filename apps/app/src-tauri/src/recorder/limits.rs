@@ -0,0 +1,134 @@
+use super::convert::TARGET_SAMPLE_RATE;
+use serde::Deserialize;
+
+/// Caps on how much audio a recording session may hold. Every cap is opt-in:
+/// fields left out by the frontend or set to `null` leave recordings uncapped.
+/// An hour of 16kHz mono is about 230MB.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RecordingLimits {
+    pub max_duration_secs: Option<f32>,
+    /// Size of the 16 kHz mono f32 buffer, in bytes
+    pub max_bytes: Option<u64>,
+}
+
+/// Which cap a recording ran into.
+#[derive(Debug, Clone, Copy)]
+pub enum LimitKind {
+    Duration,
+    Bytes,
+}
+
+impl RecordingLimits {
+    /// The tighter of the two caps as a sample count, and which cap it is.
+    pub fn max_samples(&self) -> Option<(usize, LimitKind)> {
+        let by_duration = self.max_duration_secs.map(|secs| {
+            (
+                (secs.max(0.0) * TARGET_SAMPLE_RATE as f32) as usize,
+                LimitKind::Duration,
+            )
+        });
+        let by_bytes = self.max_bytes.map(|bytes| {
+            (
+                (bytes / std::mem::size_of::<f32>() as u64) as usize,
+                LimitKind::Bytes,
+            )
+        });
+        [by_duration, by_bytes]
+            .into_iter()
+            .flatten()
+            .min_by_key(|(max_samples, _)| *max_samples)
+    }
+}
+
+/// Counts converted samples and fires once a recording reaches its cap.
+pub struct LimitWatch {
+    max_samples: usize,
+    samples: usize,
+    on_limit: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl LimitWatch {
+    pub fn new(max_samples: usize, on_limit: impl FnOnce() + Send + 'static) -> Self {
+        Self {
+            max_samples,
+            samples: 0,
+            on_limit: Some(Box::new(on_limit)),
+        }
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        self.samples += samples.len();
+        if self.samples >= self.max_samples {
+            if let Some(on_limit) = self.on_limit.take() {
+                on_limit();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn limits(max_duration_secs: Option<f32>, max_bytes: Option<u64>) -> RecordingLimits {
+        RecordingLimits {
+            max_duration_secs,
+            max_bytes,
+        }
+    }
+
+    #[test]
+    fn uncapped_by_default() {
+        assert!(RecordingLimits::default().max_samples().is_none());
+    }
+
+    #[test]
+    fn each_cap_converts_to_samples() {
+        let (samples, kind) = limits(Some(2.5), None).max_samples().unwrap();
+        assert_eq!(samples, 40_000);
+        assert!(matches!(kind, LimitKind::Duration));
+
+        let (samples, kind) = limits(None, Some(64_000)).max_samples().unwrap();
+        assert_eq!(samples, 16_000);
+        assert!(matches!(kind, LimitKind::Bytes));
+    }
+
+    #[test]
+    fn tighter_cap_wins() {
+        // 10 seconds against 5 seconds' worth of bytes
+        let (samples, kind) = limits(Some(10.0), Some(320_000)).max_samples().unwrap();
+        assert_eq!(samples, 80_000);
+        assert!(matches!(kind, LimitKind::Bytes));
+
+        // 1 second against 5 seconds' worth of bytes
+        let (samples, kind) = limits(Some(1.0), Some(320_000)).max_samples().unwrap();
+        assert_eq!(samples, 16_000);
+        assert!(matches!(kind, LimitKind::Duration));
+    }
+
+    #[test]
+    fn negative_duration_is_an_immediate_cap() {
+        let (samples, _) = limits(Some(-3.0), None).max_samples().unwrap();
+        assert_eq!(samples, 0);
+    }
+
+    #[test]
+    fn watch_fires_once_at_the_limit() {
+        let fired = Arc::new(AtomicUsize::new(0));
+        let counter = fired.clone();
+        let mut watch = LimitWatch::new(1_000, move || {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+
+        watch.push(&[0.0; 600]);
+        watch.push(&[0.0; 399]);
+        assert_eq!(fired.load(Ordering::Relaxed), 0);
+        watch.push(&[0.0; 1]);
+        assert_eq!(fired.load(Ordering::Relaxed), 1);
+        watch.push(&[0.0; 5_000]);
+        assert_eq!(fired.load(Ordering::Relaxed), 1);
+    }
+}
@@ -1,10 +1,38 @@
+pub mod capture;
 pub mod commands;
+pub mod config;
+pub mod convert;
+pub mod devices;
+pub mod error;
+pub mod events;
+pub mod level;
+pub mod limits;
+pub mod recovery;
+pub mod silence;
+pub mod spill;
+pub mod state;
 pub mod thread;
+pub mod vad;
+pub mod wav;
 
 pub use commands::{
-    cancel_recording, close_recording_session, close_thread, ensure_thread_initialized,
-    enumerate_recording_devices, init_recording_session, start_recording, stop_recording,
-    RecorderError,
+    cancel_recording, close_recording_session, close_thread, delete_recording,
+    discard_recoverable_recording, ensure_thread_initialized, enumerate_recording_devices,
+    get_device_capabilities, get_recorder_state, init_recording_session, list_recordings,
+    list_recoverable_recordings, pause_recording, restart_audio_thread, resume_recording,
+    start_background_recording, start_recording, stop_recording, stop_recording_by_id,
+    test_recording_device,
 };
+pub use devices::{spawn_device_watcher, DeviceInfo, DEVICES_CHANGED_EVENT};
+pub use error::{AudioError, RecorderError};
 
-pub use thread::{AudioCommand, AudioResponse, RecordingSession};
+pub use limits::RecordingLimits;
+pub use recovery::{recover_partial_recordings, RecoverableRecording};
+pub use silence::SilenceConfig;
+pub use spill::{delete_old_recordings, RECORDING_RETENTION};
+pub use state::{RecorderState, RECORDER_STATE_CHANGED_EVENT};
+pub use thread::{
+    ActiveRecording, AudioCommand, AudioRequest, AudioResponse, AudioThread, RecordingSession,
+};
+pub use vad::VadConfig;
+pub use wav::{encode_wav_pcm16, RecordedAudio};
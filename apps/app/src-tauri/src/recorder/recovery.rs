@@ -0,0 +1,279 @@
+use super::spill::{file_stem, recordings_dir, PARTIAL_EXTENSION};
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tauri::AppHandle;
+use tracing::{info, warn};
+
+/// Extension of a partial recording whose header has been repaired.
+const RECOVERED_EXTENSION: &str = "recovered.wav";
+
+/// A recording interrupted by a crash that can still be transcribed.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoverableRecording {
+    pub recording_id: String,
+    pub path: PathBuf,
+    pub duration_secs: f32,
+    /// When the file was last written to, in milliseconds since the Unix epoch
+    pub modified_at_ms: Option<u64>,
+}
+
+pub fn recovered_path(dir: &Path, recording_id: &str) -> PathBuf {
+    dir.join(format!(
+        "{}.{}",
+        file_stem(recording_id),
+        RECOVERED_EXTENSION
+    ))
+}
+
+/// The recording ID of a file named `<id>.<extension>`.
+fn recording_id(path: &Path, extension: &str) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    let id = name.strip_suffix(extension)?.strip_suffix('.')?;
+    Some(id.to_string())
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// Finds the block alignment and the offset of the sample data in a WAV header.
+fn parse_header(header: &[u8]) -> Option<(u16, usize)> {
+    if header.get(0..4)? != b"RIFF" || header.get(8..12)? != b"WAVE" {
+        return None;
+    }
+    let mut block_align = None;
+    let mut offset = 12;
+    loop {
+        let id = header.get(offset..offset + 4)?;
+        let size = read_u32(header, offset + 4)? as usize;
+        let body = offset + 8;
+        match id {
+            b"fmt " => block_align = read_u16(header, body + 12),
+            b"data" => return Some((block_align?, body)),
+            _ => {}
+        }
+        // Chunks are padded to an even size
+        offset = body + size + (size & 1);
+    }
+}
+
+/// Rewrites the RIFF and data chunk sizes of a WAV file that was never
+/// finalized, dropping any partially written trailing frame. Returns the
+/// number of audio bytes kept, or `None` if the file isn't a usable WAV.
+pub fn repair_wav_header(path: &Path) -> io::Result<Option<u64>> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let mut header = Vec::with_capacity(4096);
+    (&mut file).take(4096).read_to_end(&mut header)?;
+
+    let Some((block_align, data_offset)) = parse_header(&header) else {
+        return Ok(None);
+    };
+    let block_align = u64::from(block_align.max(1));
+    let data_offset = data_offset as u64;
+    let file_len = file.metadata()?.len();
+    let data_len = file_len.saturating_sub(data_offset) / block_align * block_align;
+    let (Ok(riff_size), Ok(data_size)) = (
+        u32::try_from(data_offset + data_len - 8),
+        u32::try_from(data_len),
+    ) else {
+        return Ok(None);
+    };
+
+    file.set_len(data_offset + data_len)?;
+    file.seek(SeekFrom::Start(4))?;
+    file.write_all(&riff_size.to_le_bytes())?;
+    file.seek(SeekFrom::Start(data_offset - 4))?;
+    file.write_all(&data_size.to_le_bytes())?;
+    file.sync_all()?;
+    Ok(Some(data_len))
+}
+
+/// Repairs every partial recording left behind by a crash or force-quit and
+/// marks it as recoverable. Must run before any recording starts, since it
+/// can't tell a partial file that is still being written from an orphan.
+pub fn recover_partial_recordings(app: &AppHandle) -> io::Result<usize> {
+    let dir = recordings_dir(app)?;
+    let mut recovered = 0;
+    // One bad file shouldn't keep the others from being recovered
+    for entry in std::fs::read_dir(&dir)? {
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(e) => {
+                warn!("Failed to read recordings directory entry: {}", e);
+                continue;
+            }
+        };
+        let Some(id) = recording_id(&path, PARTIAL_EXTENSION) else {
+            continue;
+        };
+        match repair_wav_header(&path) {
+            Ok(Some(data_len)) if data_len > 0 => {
+                if let Err(e) = std::fs::rename(&path, recovered_path(&dir, &id)) {
+                    warn!("Failed to mark recording {} as recovered: {}", id, e);
+                    continue;
+                }
+                info!("Recovered interrupted recording {}", id);
+                recovered += 1;
+            }
+            Ok(_) => {
+                warn!("Discarding interrupted recording {} with no audio", id);
+                if let Err(e) = std::fs::remove_file(&path) {
+                    warn!("Failed to delete interrupted recording {}: {}", id, e);
+                }
+            }
+            Err(e) => warn!("Failed to repair interrupted recording {}: {}", id, e),
+        }
+    }
+    Ok(recovered)
+}
+
+/// Lists recordings recovered by [`recover_partial_recordings`], oldest first.
+pub fn list_recoverable(app: &AppHandle) -> io::Result<Vec<RecoverableRecording>> {
+    let dir = recordings_dir(app)?;
+    let mut recordings = Vec::new();
+    for entry in std::fs::read_dir(&dir)? {
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(e) => {
+                warn!("Failed to read recordings directory entry: {}", e);
+                continue;
+            }
+        };
+        let Some(recording_id) = recording_id(&path, RECOVERED_EXTENSION) else {
+            continue;
+        };
+        let duration_secs = match hound::WavReader::open(&path) {
+            Ok(reader) => reader.duration() as f32 / reader.spec().sample_rate.max(1) as f32,
+            Err(e) => {
+                warn!(
+                    "Skipping unreadable recovered recording {}: {}",
+                    recording_id, e
+                );
+                continue;
+            }
+        };
+        let modified_at_ms = std::fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|since_epoch| since_epoch.as_millis() as u64);
+        recordings.push(RecoverableRecording {
+            recording_id,
+            path,
+            duration_secs,
+            modified_at_ms,
+        });
+    }
+    recordings.sort_by_key(|recording| recording.modified_at_ms);
+    Ok(recordings)
+}
+
+/// Deletes a recovered recording once the frontend has dealt with it.
+pub fn discard_recoverable(app: &AppHandle, recording_id: &str) -> io::Result<()> {
+    let dir = recordings_dir(app)?;
+    let path = dir.join(format!("{}.{}", recording_id, RECOVERED_EXTENSION));
+    // Refuse IDs that would escape the recordings dir
+    if path.parent() != Some(dir.as_path()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Invalid recording ID",
+        ));
+    }
+    std::fs::remove_file(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recorder::wav::{read_wav_file, wav_spec};
+    use hound::WavWriter;
+
+    /// Writes `samples` as a WAV file whose header still describes only the
+    /// first sample, the way a crash leaves a partial recording.
+    fn write_unfinalized(path: &Path, samples: &[f32]) {
+        let mut writer = WavWriter::create(path, wav_spec(16_000, 1)).unwrap();
+        for &sample in samples {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+        let mut file = OpenOptions::new().write(true).open(path).unwrap();
+        file.seek(SeekFrom::Start(4)).unwrap();
+        file.write_all(&64u32.to_le_bytes()).unwrap();
+        // Float samples get the 40 byte extensible fmt chunk
+        file.seek(SeekFrom::Start(64)).unwrap();
+        file.write_all(&4u32.to_le_bytes()).unwrap();
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}-{}.wav", name, std::process::id()))
+    }
+
+    #[test]
+    fn header_points_at_the_data_chunk() {
+        let path = temp_path("header");
+        WavWriter::create(&path, wav_spec(16_000, 1))
+            .unwrap()
+            .finalize()
+            .unwrap();
+        let header = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        // Past the 40 byte extensible fmt chunk hound writes for floats
+        assert_eq!(parse_header(&header), Some((4, 68)));
+    }
+
+    #[test]
+    fn header_needs_riff_and_data_chunks() {
+        assert_eq!(parse_header(b"not a wav file"), None);
+        let path = temp_path("no-data");
+        WavWriter::create(&path, wav_spec(16_000, 1))
+            .unwrap()
+            .finalize()
+            .unwrap();
+        let header = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        // Cut off before the data chunk
+        assert_eq!(parse_header(&header[..36]), None);
+    }
+
+    #[test]
+    fn repair_recovers_a_truncated_recording() {
+        let path = temp_path("truncated");
+        let samples: Vec<f32> = (0..5_000).map(|i| i as f32 / 5_000.0).collect();
+        write_unfinalized(&path, &samples);
+        // Lose half of the last sample, as an interrupted write would
+        let len = std::fs::metadata(&path).unwrap().len();
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - 2)
+            .unwrap();
+
+        let data_len = repair_wav_header(&path).unwrap();
+        let recovered = read_wav_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(data_len, Some(4_999 * 4));
+        assert_eq!(recovered.samples, samples[..4_999]);
+    }
+
+    #[test]
+    fn repair_rejects_files_that_arent_wav() {
+        let path = temp_path("garbage");
+        std::fs::write(&path, b"definitely not audio").unwrap();
+        let repaired = repair_wav_header(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(repaired, None);
+    }
+}
@@ -0,0 +1,251 @@
+use super::convert::TARGET_SAMPLE_RATE;
+use super::level::rms;
+use serde::Deserialize;
+use std::ops::Range;
+use tracing::debug;
+
+/// Silence is measured in 30ms frames of 16kHz mono.
+const FRAME_SAMPLES: usize = 480;
+
+/// Silence handling for a single recording. Fields left out by the frontend
+/// keep their defaults, which leave the audio untouched.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SilenceConfig {
+    /// RMS level (0.0 to 1.0) below which a frame counts as silence
+    pub threshold: f32,
+    /// Trim leading and trailing silence from the returned audio
+    pub trim: bool,
+    /// Silence kept on either side of the audio when trimming
+    pub padding_ms: u32,
+    /// Stop capturing once the input has been silent for this long
+    pub auto_stop_secs: Option<f32>,
+}
+
+impl Default for SilenceConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 0.01,
+            trim: false,
+            padding_ms: 200,
+            auto_stop_secs: None,
+        }
+    }
+}
+
+/// Finds the audible part of a recording a packet at a time, so a recording
+/// on disk can be scanned without loading it whole.
+pub struct SilenceScan {
+    threshold: f32,
+    padding: usize,
+    frame: Vec<f32>,
+    /// Whole frames scanned so far
+    frames: usize,
+    first_audible: Option<usize>,
+    last_audible: Option<usize>,
+}
+
+impl SilenceScan {
+    pub fn new(config: &SilenceConfig) -> Self {
+        Self {
+            threshold: config.threshold,
+            padding: TARGET_SAMPLE_RATE as usize * config.padding_ms as usize / 1000,
+            frame: Vec::with_capacity(FRAME_SAMPLES),
+            frames: 0,
+            first_audible: None,
+            last_audible: None,
+        }
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        let mut remaining = samples;
+        while !remaining.is_empty() {
+            let take = (FRAME_SAMPLES - self.frame.len()).min(remaining.len());
+            self.frame.extend_from_slice(&remaining[..take]);
+            remaining = &remaining[take..];
+            if self.frame.len() == FRAME_SAMPLES {
+                self.scan_frame();
+            }
+        }
+    }
+
+    /// The part of the scanned audio to keep, with `padding_ms` on either
+    /// side of the audible part, or `None` if it was silent throughout.
+    pub fn finish(mut self) -> Option<Range<usize>> {
+        let len = self.frames * FRAME_SAMPLES + self.frame.len();
+        if !self.frame.is_empty() {
+            self.scan_frame();
+        }
+        let (first, last) = (self.first_audible?, self.last_audible?);
+        let start = (first * FRAME_SAMPLES).saturating_sub(self.padding);
+        let end = ((last + 1) * FRAME_SAMPLES + self.padding).min(len);
+        Some(start..end)
+    }
+
+    fn scan_frame(&mut self) {
+        if rms(&self.frame) >= self.threshold {
+            self.first_audible.get_or_insert(self.frames);
+            self.last_audible = Some(self.frames);
+        }
+        self.frames += 1;
+        self.frame.clear();
+    }
+}
+
+/// Removes leading and trailing silence from `samples`, keeping
+/// `config.padding_ms` around the audible part. A recording that is silent
+/// throughout ends up empty. Returns how many samples were removed from the
+/// start.
+pub fn trim_silence(samples: &mut Vec<f32>, config: &SilenceConfig) -> usize {
+    let mut scan = SilenceScan::new(config);
+    scan.push(samples);
+    let Some(keep) = scan.finish() else {
+        debug!("Trimmed {} samples of silence", samples.len());
+        let trimmed = samples.len();
+        samples.clear();
+        return trimmed;
+    };
+
+    debug!(
+        "Trimmed {} leading and {} trailing samples of silence",
+        keep.start,
+        samples.len() - keep.end
+    );
+    samples.truncate(keep.end);
+    samples.drain(..keep.start);
+    keep.start
+}
+
+/// Watches converted audio and fires once the input has stayed below the
+/// silence threshold for the configured time.
+pub struct SilenceWatch {
+    threshold: f32,
+    limit: usize,
+    silent_samples: usize,
+    frame: Vec<f32>,
+    on_timeout: Option<Box<dyn FnOnce(f32) + Send>>,
+}
+
+impl SilenceWatch {
+    /// Returns `None` when `config` doesn't ask for auto-stop. `on_timeout`
+    /// receives the length of the silence in seconds.
+    pub fn new(
+        config: &SilenceConfig,
+        on_timeout: impl FnOnce(f32) + Send + 'static,
+    ) -> Option<Self> {
+        let limit_secs = config.auto_stop_secs?;
+        Some(Self {
+            threshold: config.threshold,
+            limit: (limit_secs.max(0.0) * TARGET_SAMPLE_RATE as f32) as usize,
+            silent_samples: 0,
+            frame: Vec::with_capacity(FRAME_SAMPLES),
+            on_timeout: Some(Box::new(on_timeout)),
+        })
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        let mut remaining = samples;
+        while !remaining.is_empty() && self.on_timeout.is_some() {
+            let take = (FRAME_SAMPLES - self.frame.len()).min(remaining.len());
+            self.frame.extend_from_slice(&remaining[..take]);
+            remaining = &remaining[take..];
+
+            if self.frame.len() == FRAME_SAMPLES {
+                if rms(&self.frame) < self.threshold {
+                    self.silent_samples += FRAME_SAMPLES;
+                } else {
+                    self.silent_samples = 0;
+                }
+                self.frame.clear();
+
+                if self.silent_samples >= self.limit {
+                    if let Some(on_timeout) = self.on_timeout.take() {
+                        on_timeout(self.silent_samples as f32 / TARGET_SAMPLE_RATE as f32);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    fn config() -> SilenceConfig {
+        SilenceConfig {
+            trim: true,
+            ..SilenceConfig::default()
+        }
+    }
+
+    #[test]
+    fn trim_keeps_padding_around_the_audible_part() {
+        // 33 silent frames on either side of 20 audible ones
+        let mut samples = [vec![0.0; 15_840], vec![0.5; 9_600], vec![0.0; 15_840]].concat();
+        let removed = trim_silence(&mut samples, &config());
+        // 200ms of padding is 3_200 samples
+        assert_eq!(removed, 15_840 - 3_200);
+        assert_eq!(samples.len(), 3_200 + 9_600 + 3_200);
+        assert_eq!(samples[3_200], 0.5);
+    }
+
+    #[test]
+    fn trim_stops_at_the_edges_of_the_recording() {
+        let mut samples = [vec![0.5; 960], vec![0.0; 960]].concat();
+        let removed = trim_silence(&mut samples, &config());
+        assert_eq!(removed, 0);
+        assert_eq!(samples.len(), 1_920);
+    }
+
+    #[test]
+    fn scan_gives_the_same_range_in_any_packet_size() {
+        let samples = [vec![0.0; 10_000], vec![0.5; 5_000], vec![0.0; 10_000]].concat();
+        let mut whole = SilenceScan::new(&config());
+        whole.push(&samples);
+        let expected = whole.finish();
+        assert!(expected.is_some());
+
+        let mut packets = SilenceScan::new(&config());
+        for packet in samples.chunks(333) {
+            packets.push(packet);
+        }
+        assert_eq!(packets.finish(), expected);
+    }
+
+    #[test]
+    fn trim_empties_a_silent_recording() {
+        let mut samples = vec![0.001; 16_000];
+        assert_eq!(trim_silence(&mut samples, &config()), 16_000);
+        assert!(samples.is_empty());
+    }
+
+    #[test]
+    fn watch_needs_an_auto_stop_time() {
+        assert!(SilenceWatch::new(&SilenceConfig::default(), |_| {}).is_none());
+    }
+
+    #[test]
+    fn watch_fires_once_after_continuous_silence() {
+        let (tx, rx) = mpsc::channel();
+        let config = SilenceConfig {
+            auto_stop_secs: Some(0.3),
+            ..SilenceConfig::default()
+        };
+        let mut watch = SilenceWatch::new(&config, move |secs| tx.send(secs).unwrap()).unwrap();
+
+        // Sound in between resets the count
+        watch.push(&vec![0.0; 4_000]);
+        watch.push(&vec![0.5; 480]);
+        watch.push(&vec![0.0; 4_000]);
+        assert!(rx.try_recv().is_err());
+
+        watch.push(&vec![0.0; 2_000]);
+        let secs = rx.try_recv().unwrap();
+        assert!((0.3..0.33).contains(&secs), "{}", secs);
+
+        watch.push(&vec![0.0; 16_000]);
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,413 @@
+use super::convert::{TARGET_CHANNELS, TARGET_SAMPLE_RATE};
+use super::recovery::repair_wav_header;
+use super::wav::{read_wav_chunks, wav_spec};
+use hound::WavWriter;
+use serde::Serialize;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager};
+use tracing::{error, info, warn};
+use zeroize::Zeroize;
+
+/// Directory under the app data dir that recordings are written to.
+const RECORDINGS_DIR: &str = "recordings";
+
+/// Extension of a recording that is still being written.
+pub const PARTIAL_EXTENSION: &str = "partial.wav";
+
+/// The WAV header is rewritten after this many samples (one second), so a
+/// crash loses at most about a second of audio.
+const HEADER_FLUSH_SAMPLES: usize = TARGET_SAMPLE_RATE as usize;
+
+/// Audio kept in memory once it's on disk: ten seconds, so a session can
+/// hold at most twice that plus whatever hasn't been flushed yet.
+const MEMORY_TAIL_SAMPLES: usize = 10 * TARGET_SAMPLE_RATE as usize;
+
+/// Finished recordings older than this are deleted on startup. Recovered
+/// recordings are left for the user to deal with.
+pub const RECORDING_RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Creates the recordings directory if needed and returns it.
+pub fn recordings_dir(app: &AppHandle) -> std::io::Result<PathBuf> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(std::io::Error::other)?
+        .join(RECORDINGS_DIR);
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Recording IDs come from the frontend, so keep only characters that are
+/// safe in a file name.
+pub fn file_stem(recording_id: &str) -> String {
+    recording_id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect()
+}
+
+pub fn recording_path(dir: &Path, recording_id: &str) -> PathBuf {
+    dir.join(format!("{}.wav", file_stem(recording_id)))
+}
+
+fn partial_path(dir: &Path, recording_id: &str) -> PathBuf {
+    dir.join(format!("{}.{}", file_stem(recording_id), PARTIAL_EXTENSION))
+}
+
+/// A finished recording on disk.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingFile {
+    pub recording_id: String,
+    pub path: PathBuf,
+    pub duration_secs: f32,
+    /// Offsets, in seconds, at which the recording was paused
+    pub segment_markers: Vec<f32>,
+}
+
+/// Streams converted audio into a 16 kHz mono WAV file while recording. The
+/// file keeps a `.partial.wav` extension until the recording is finished.
+pub struct SpillWriter {
+    writer: WavWriter<BufWriter<File>>,
+    partial_path: PathBuf,
+    path: PathBuf,
+    /// Samples written so far, and how many of them have been flushed
+    written: usize,
+    flushed: usize,
+}
+
+impl SpillWriter {
+    pub fn create(dir: &Path, recording_id: &str) -> Result<Self, hound::Error> {
+        let partial_path = partial_path(dir, recording_id);
+        let writer =
+            WavWriter::create(&partial_path, wav_spec(TARGET_SAMPLE_RATE, TARGET_CHANNELS))?;
+        Ok(Self {
+            writer,
+            partial_path,
+            path: recording_path(dir, recording_id),
+            written: 0,
+            flushed: 0,
+        })
+    }
+
+    pub fn push(&mut self, samples: &[f32]) -> Result<(), hound::Error> {
+        for &sample in samples {
+            self.writer.write_sample(sample)?;
+        }
+        self.written += samples.len();
+        if self.written - self.flushed >= HEADER_FLUSH_SAMPLES {
+            self.writer.flush()?;
+            self.flushed = self.written;
+        }
+        Ok(())
+    }
+
+    /// Finalizes the header and moves the file to its final name.
+    pub fn finish(self) -> Result<PathBuf, hound::Error> {
+        self.writer.finalize()?;
+        std::fs::rename(&self.partial_path, &self.path)?;
+        Ok(self.path)
+    }
+
+    /// Deletes the partial file of a canceled recording.
+    pub fn discard(self) -> std::io::Result<()> {
+        drop(self.writer);
+        std::fs::remove_file(&self.partial_path)
+    }
+}
+
+/// The audio of a stopped recording.
+pub enum FinishedAudio {
+    /// All of it, in a finished WAV file
+    File { path: PathBuf, len: usize },
+    /// All of it, in memory
+    Memory(Vec<f32>),
+}
+
+/// Converted 16 kHz mono audio of the current recording, filled by the
+/// capture consumer thread. While the recording is being written to disk,
+/// only the last few seconds are kept in memory; the rest is read back from
+/// the file if the file turns out to be unusable.
+#[derive(Default)]
+pub struct SessionAudio {
+    samples: Vec<f32>,
+    /// Samples dropped from the front of `samples` because they're on disk
+    offset: usize,
+    spill: Option<SpillWriter>,
+    /// Partial file of a failed spill that holds the first `offset` samples
+    abandoned: Option<PathBuf>,
+}
+
+impl SessionAudio {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            samples: Vec::with_capacity(capacity),
+            ..Self::default()
+        }
+    }
+
+    /// Samples captured so far, including the ones only on disk.
+    pub fn len(&self) -> usize {
+        self.offset + self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Clears the previous recording and starts the next one, writing it to
+    /// `spill` if given.
+    pub fn start(&mut self, spill: Option<SpillWriter>, capacity: usize) {
+        self.discard();
+        self.samples.reserve(capacity);
+        self.spill = spill;
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        self.samples.extend_from_slice(samples);
+        let Some(spill) = self.spill.as_mut() else {
+            return;
+        };
+        if let Err(e) = spill.push(samples) {
+            warn!("Stopped writing recording to disk: {}", e);
+            self.abandon_spill();
+            return;
+        }
+        if self.samples.len() >= 2 * MEMORY_TAIL_SAMPLES {
+            // Only audio that made it to disk can be dropped
+            let on_disk = spill.flushed - self.offset;
+            let dropped = on_disk.min(self.samples.len() - MEMORY_TAIL_SAMPLES);
+            self.samples.drain(..dropped);
+            self.offset += dropped;
+        }
+    }
+
+    /// Takes the samples of a capture that was never written to disk.
+    pub fn take_samples(&mut self) -> Vec<f32> {
+        debug_assert!(self.offset == 0 && self.spill.is_none());
+        std::mem::take(&mut self.samples)
+    }
+
+    /// Finishes the recording file, or gathers all of the audio in memory if
+    /// writing it failed along the way.
+    pub fn finish(mut self) -> Result<FinishedAudio, hound::Error> {
+        if let Some(spill) = self.spill.take() {
+            let len = spill.written;
+            let partial_path = spill.partial_path.clone();
+            match spill.finish() {
+                Ok(path) if len == self.len() => return Ok(FinishedAudio::File { path, len }),
+                Ok(path) => self.abandoned = Some(path),
+                Err(e) => {
+                    error!("Failed to finish recording file: {}", e);
+                    self.abandoned = Some(partial_path);
+                }
+            }
+        }
+        let Some(path) = self.abandoned.take() else {
+            return Ok(FinishedAudio::Memory(std::mem::take(&mut self.samples)));
+        };
+        if self.offset == 0 {
+            remove_abandoned(&path);
+            return Ok(FinishedAudio::Memory(std::mem::take(&mut self.samples)));
+        }
+
+        // The start of the recording is only on disk
+        repair_wav_header(&path)?;
+        let mut samples = Vec::with_capacity(self.len());
+        read_wav_chunks(&path, self.offset, |chunk| samples.extend_from_slice(chunk))?;
+        if samples.len() != self.offset {
+            // Leave the file for recovery to pick up on the next launch
+            return Err(hound::Error::FormatError(
+                "recording file is shorter than the audio written to it",
+            ));
+        }
+        samples.append(&mut self.samples);
+        remove_abandoned(&path);
+        Ok(FinishedAudio::Memory(samples))
+    }
+
+    /// Drops the recording, overwriting it in memory and deleting its file.
+    pub fn discard(&mut self) {
+        // Don't leave the recording lying around in freed memory. A plain
+        // fill could be optimized away since the buffer is freed right after.
+        self.samples.zeroize();
+        self.samples = Vec::new();
+        self.offset = 0;
+        if let Some(spill) = self.spill.take() {
+            if let Err(e) = spill.discard() {
+                warn!("Failed to delete canceled recording file: {}", e);
+            }
+        }
+        if let Some(path) = self.abandoned.take() {
+            remove_abandoned(&path);
+        }
+    }
+
+    fn abandon_spill(&mut self) {
+        let Some(spill) = self.spill.take() else {
+            return;
+        };
+        if self.offset == 0 {
+            // Everything is still in memory, so the file is of no use
+            if let Err(e) = spill.discard() {
+                warn!("Failed to delete abandoned recording file: {}", e);
+            }
+        } else {
+            self.abandoned = Some(spill.partial_path.clone());
+        }
+    }
+}
+
+fn remove_abandoned(path: &Path) {
+    if let Err(e) = std::fs::remove_file(path) {
+        warn!(
+            "Failed to delete abandoned recording file {}: {}",
+            path.display(),
+            e
+        );
+    }
+}
+
+/// Deletes the file of a stopped recording once the frontend has read it.
+/// A recording that was already deleted is not an error.
+pub fn delete_recording_file(app: &AppHandle, recording_id: &str) -> std::io::Result<()> {
+    let path = recording_path(&recordings_dir(app)?, recording_id);
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Deletes finished recordings last written more than `max_age` ago. Partial
+/// and recovered recordings are left alone.
+pub fn delete_old_recordings(app: &AppHandle, max_age: Duration) -> std::io::Result<usize> {
+    let dir = recordings_dir(app)?;
+    let mut deleted = 0;
+    for entry in std::fs::read_dir(&dir)? {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        let path = entry.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        // Only `<id>.wav`, never `<id>.partial.wav` or `<id>.recovered.wav`
+        let is_finished = name
+            .strip_suffix(".wav")
+            .is_some_and(|stem| !stem.is_empty() && stem == file_stem(stem));
+        if !is_finished {
+            continue;
+        }
+        let age = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+        if age.is_none_or(|age| age < max_age) {
+            continue;
+        }
+        match std::fs::remove_file(&path) {
+            Ok(()) => deleted += 1,
+            Err(e) => warn!("Failed to delete old recording {}: {}", path.display(), e),
+        }
+    }
+    if deleted > 0 {
+        info!("Deleted {} recordings older than {:?}", deleted, max_age);
+    }
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recorder::wav::read_wav_file;
+
+    /// A fresh directory for one test's recording files.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("spill-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn ramp(seconds: usize) -> Vec<f32> {
+        (0..seconds * TARGET_SAMPLE_RATE as usize)
+            .map(|i| (i % 1_000) as f32 / 1_000.0)
+            .collect()
+    }
+
+    fn record(audio: &mut SessionAudio, samples: &[f32]) {
+        for packet in samples.chunks(1_600) {
+            audio.push(packet);
+        }
+    }
+
+    #[test]
+    fn spilled_audio_is_not_kept_in_memory() {
+        let dir = test_dir("bounded");
+        let mut audio = SessionAudio::default();
+        audio.start(Some(SpillWriter::create(&dir, "long").unwrap()), 0);
+        let samples = ramp(60);
+        record(&mut audio, &samples);
+
+        assert_eq!(audio.len(), samples.len());
+        assert!(audio.samples.len() < 2 * MEMORY_TAIL_SAMPLES + HEADER_FLUSH_SAMPLES);
+        let FinishedAudio::File { path, len } = audio.finish().unwrap() else {
+            panic!("expected the recording file");
+        };
+        assert_eq!(len, samples.len());
+        assert_eq!(read_wav_file(&path).unwrap().samples, samples);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn failed_spill_is_read_back_into_memory() {
+        let dir = test_dir("failed");
+        let mut audio = SessionAudio::default();
+        audio.start(Some(SpillWriter::create(&dir, "failed").unwrap()), 0);
+        let samples = ramp(30);
+        record(&mut audio, &samples[..25 * 16_000]);
+        assert!(audio.offset > 0);
+        audio.abandon_spill();
+        record(&mut audio, &samples[25 * 16_000..]);
+
+        let FinishedAudio::Memory(recorded) = audio.finish().unwrap() else {
+            panic!("expected the audio in memory");
+        };
+        assert_eq!(recorded, samples);
+        // The partial file is deleted once read back
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn failed_spill_is_deleted_while_memory_has_everything() {
+        let dir = test_dir("early");
+        let mut audio = SessionAudio::default();
+        audio.start(Some(SpillWriter::create(&dir, "early").unwrap()), 0);
+        record(&mut audio, &ramp(1));
+        audio.abandon_spill();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        let FinishedAudio::Memory(recorded) = audio.finish().unwrap() else {
+            panic!("expected the audio in memory");
+        };
+        assert_eq!(recorded.len(), 16_000);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn discard_deletes_the_recording_file() {
+        let dir = test_dir("discard");
+        let mut audio = SessionAudio::default();
+        audio.start(Some(SpillWriter::create(&dir, "canceled").unwrap()), 0);
+        record(&mut audio, &ramp(25));
+        audio.discard();
+        assert!(audio.is_empty());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,204 @@
+use super::events::emit;
+use serde::Serialize;
+use tauri::AppHandle;
+use thiserror::Error;
+use tracing::debug;
+
+/// Emitted with the new [`RecorderState`] on every state transition.
+pub const RECORDER_STATE_CHANGED_EVENT: &str = "recorder-state-changed";
+
+/// Mirrors `WhisperingRecordingState` on the frontend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum RecorderState {
+    #[serde(rename = "IDLE")]
+    Idle,
+    #[serde(rename = "SESSION")]
+    Session,
+    #[serde(rename = "SESSION+RECORDING")]
+    SessionRecording,
+}
+
+impl RecorderState {
+    /// Returns the state `action` leads to from this one.
+    pub fn next(self, action: RecorderAction) -> Result<RecorderState, InvalidTransition> {
+        use RecorderAction as A;
+        use RecorderState as S;
+
+        match (self, action) {
+            (S::SessionRecording, A::InitSession) => Err(InvalidTransition::AlreadyRecording),
+            (_, A::InitSession) => Ok(S::Session),
+            // The recording's file would be left behind and recovered on the next launch
+            (S::SessionRecording, A::CloseSession) => Err(InvalidTransition::AlreadyRecording),
+            (_, A::CloseSession) => Ok(S::Idle),
+            (S::Idle, A::StartRecording) => Err(InvalidTransition::NoSession),
+            (S::Session, A::StartRecording) => Ok(S::SessionRecording),
+            (S::SessionRecording, A::StartRecording) => Err(InvalidTransition::AlreadyRecording),
+            (S::SessionRecording, A::StopRecording | A::CancelRecording) => Ok(S::Session),
+            (_, A::StopRecording | A::CancelRecording) => Err(InvalidTransition::NotRecording),
+            // A paused recording is still open, so pausing doesn't change the state
+            (S::SessionRecording, A::PauseRecording | A::ResumeRecording) => {
+                Ok(S::SessionRecording)
+            }
+            (_, A::PauseRecording | A::ResumeRecording) => Err(InvalidTransition::NotRecording),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum RecorderAction {
+    InitSession,
+    CloseSession,
+    StartRecording,
+    StopRecording,
+    CancelRecording,
+    PauseRecording,
+    ResumeRecording,
+}
+
+#[derive(Debug, Clone, Copy, Error)]
+pub enum InvalidTransition {
+    #[error("No recording session has been initialized")]
+    NoSession,
+    #[error("A recording is already in progress")]
+    AlreadyRecording,
+    #[error("No recording is in progress")]
+    NotRecording,
+    #[error("The recording is already paused")]
+    AlreadyPaused,
+    #[error("The recording is not paused")]
+    NotPaused,
+    #[error("A recording with this ID is already in progress")]
+    DuplicateRecording,
+    #[error("No recording with this ID is in progress")]
+    RecordingNotFound,
+    #[error("The recording stopped on its own and can only be stopped or canceled")]
+    AutoStopped,
+    #[error("The device is already open for a session or device test")]
+    DeviceInUse,
+}
+
+impl InvalidTransition {
+    pub fn code(&self) -> &'static str {
+        match self {
+            InvalidTransition::NoSession => "NO_SESSION",
+            InvalidTransition::AlreadyRecording => "ALREADY_RECORDING",
+            InvalidTransition::NotRecording => "NOT_RECORDING",
+            InvalidTransition::AlreadyPaused => "ALREADY_PAUSED",
+            InvalidTransition::NotPaused => "NOT_PAUSED",
+            InvalidTransition::DuplicateRecording => "DUPLICATE_RECORDING",
+            InvalidTransition::RecordingNotFound => "RECORDING_NOT_FOUND",
+            InvalidTransition::AutoStopped => "AUTO_STOPPED",
+            InvalidTransition::DeviceInUse => "DEVICE_IN_USE",
+        }
+    }
+}
+
+/// Tracks the recorder state on the audio thread and notifies the frontend of changes.
+pub struct RecorderStateMachine {
+    app: AppHandle,
+    state: RecorderState,
+}
+
+impl RecorderStateMachine {
+    pub fn new(app: AppHandle) -> Self {
+        Self {
+            app,
+            state: RecorderState::Idle,
+        }
+    }
+
+    pub fn state(&self) -> RecorderState {
+        self.state
+    }
+
+    /// Returns the state `action` would lead to, without applying it.
+    pub fn next(&self, action: RecorderAction) -> Result<RecorderState, InvalidTransition> {
+        self.state.next(action)
+    }
+
+    /// Moves to `state`, emitting a change event if it differs from the current one.
+    pub fn set(&mut self, state: RecorderState) {
+        if self.state == state {
+            return;
+        }
+        debug!("Recorder state: {:?} -> {:?}", self.state, state);
+        self.state = state;
+        emit(&self.app, RECORDER_STATE_CHANGED_EVENT, state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use RecorderAction as A;
+    use RecorderState as S;
+
+    fn code(result: Result<RecorderState, InvalidTransition>) -> &'static str {
+        result.expect_err("transition should be rejected").code()
+    }
+
+    #[test]
+    fn full_recording_cycle() {
+        let mut state = S::Idle;
+        for (action, expected) in [
+            (A::InitSession, S::Session),
+            (A::StartRecording, S::SessionRecording),
+            (A::PauseRecording, S::SessionRecording),
+            (A::ResumeRecording, S::SessionRecording),
+            (A::StopRecording, S::Session),
+            (A::StartRecording, S::SessionRecording),
+            (A::CancelRecording, S::Session),
+            (A::CloseSession, S::Idle),
+        ] {
+            state = state.next(action).unwrap();
+            assert_eq!(state, expected, "after {:?}", action);
+        }
+    }
+
+    #[test]
+    fn recording_needs_a_session() {
+        assert_eq!(code(S::Idle.next(A::StartRecording)), "NO_SESSION");
+    }
+
+    #[test]
+    fn only_one_recording_at_a_time() {
+        assert_eq!(
+            code(S::SessionRecording.next(A::StartRecording)),
+            "ALREADY_RECORDING"
+        );
+        assert_eq!(
+            code(S::SessionRecording.next(A::InitSession)),
+            "ALREADY_RECORDING"
+        );
+    }
+
+    #[test]
+    fn session_cant_be_closed_while_recording() {
+        assert_eq!(
+            code(S::SessionRecording.next(A::CloseSession)),
+            "ALREADY_RECORDING"
+        );
+    }
+
+    #[test]
+    fn stopping_needs_a_recording() {
+        for state in [S::Idle, S::Session] {
+            for action in [
+                A::StopRecording,
+                A::CancelRecording,
+                A::PauseRecording,
+                A::ResumeRecording,
+            ] {
+                assert_eq!(code(state.next(action)), "NOT_RECORDING");
+            }
+        }
+    }
+
+    #[test]
+    fn idle_sessions_can_be_closed_or_reopened() {
+        for state in [S::Idle, S::Session] {
+            assert_eq!(state.next(A::CloseSession).unwrap(), S::Idle);
+        }
+        assert_eq!(S::Session.next(A::InitSession).unwrap(), S::Session);
+    }
+}
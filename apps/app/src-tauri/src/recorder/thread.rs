@@ -1,256 +1,1295 @@
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::Stream;
+use super::capture::{
+    capture_pipeline, CaptureConsumer, CaptureCounters, CaptureProducer, CaptureStats, ChunkStream,
+    VadStream,
+};
+use super::config::{
+    device_capabilities, select_input_config, CaptureConfig, DeviceCapabilities, RequestedConfig,
+};
+use super::convert::{TARGET_CHANNELS, TARGET_SAMPLE_RATE};
+use super::devices::{find_input_device, list_input_devices, DeviceInfo, DEFAULT_DEVICE_ID};
+use super::error::{AudioError, RecorderError};
+use super::events::{
+    emit, AutoStopped, DeviceLost, RecordingAutoStopped, DEVICE_LOST_EVENT,
+    RECORDING_AUTO_STOPPED_EVENT,
+};
+use super::limits::{LimitKind, LimitWatch, RecordingLimits};
+use super::silence::{trim_silence, SilenceConfig, SilenceScan, SilenceWatch};
+use super::spill::{
+    recording_path, recordings_dir, FinishedAudio, RecordingFile, SessionAudio, SpillWriter,
+};
+use super::state::{InvalidTransition, RecorderAction, RecorderState, RecorderStateMachine};
+use super::vad::VadConfig;
+use super::wav::{cut_wav_file, read_wav_chunks, write_wav_file, RecordedAudio};
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::{Device, FromSample, Host, SampleFormat, SizedSample, Stream, StreamConfig};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
-use std::sync::{
-    mpsc::{self, SendError},
-    Arc,
-};
+use std::sync::{mpsc, Arc};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tauri::AppHandle;
+use tracing::{debug, error, info, warn};
+
+const INITIAL_BUFFER_CAPACITY: usize = 1_920_000; // Pre-allocate for ~2 minutes at 16kHz mono
+
+/// Longest capture a device test will make.
+pub const MAX_TEST_SECONDS: f32 = 5.0;
 
-const INITIAL_BUFFER_CAPACITY: usize = 5_760_000; // Pre-allocate for ~2 minutes at 48kHz
+/// Stream generation used for device tests. Sessions count up from 0 and never
+/// reach it, so a failing test stream is never mistaken for the session's.
+const TEST_GENERATION: u64 = u64::MAX;
 
 #[derive(Debug)]
 pub enum AudioCommand {
     CloseThread,
+    GetRecorderState,
     EnumerateRecordingDevices,
-    InitRecordingSession(String),
+    GetDeviceCapabilities(String),
+    InitRecordingSession {
+        device_id: String,
+        requested_config: Option<RequestedConfig>,
+        failover_to_default: bool,
+        limits: RecordingLimits,
+    },
     CloseRecordingSession,
-    StartRecording,
+    /// Captures briefly from a device, independent of the current session.
+    /// Runs on its own thread so other commands aren't held up meanwhile.
+    TestRecordingDevice {
+        device_id: String,
+        seconds: f32,
+        requested_config: Option<RequestedConfig>,
+    },
+    /// Starts recording, optionally streaming audio chunks to the frontend as they're captured
+    /// and splitting them into speech segments
+    StartRecording {
+        recording_id: String,
+        stream_chunks: bool,
+        vad: Option<VadConfig>,
+        silence: Option<SilenceConfig>,
+    },
     StopRecording,
+    /// Opens `device_id` and records on it alongside the main session until
+    /// stopped by ID
+    StartBackgroundRecording {
+        recording_id: String,
+        device_id: String,
+        requested_config: Option<RequestedConfig>,
+        failover_to_default: bool,
+        limits: RecordingLimits,
+        silence: Option<SilenceConfig>,
+    },
+    ListRecordings,
+    /// Stops either the main session's recording or a background one
+    StopRecordingById(String),
+    /// Stops capturing and throws the recording away without saving it
+    CancelRecording,
+    /// Stops capturing but keeps the recording open, optionally marking the
+    /// end of a segment
+    PauseRecording {
+        mark_segment: bool,
+    },
+    ResumeRecording,
+    /// Sent by the capture consumer when a recording should stop capturing on
+    /// its own. Never answered.
+    AutoStop {
+        recording_id: String,
+        reason: RecordingAutoStopped,
+    },
+    /// Sent by the input stream's error callback. Never answered.
+    StreamFailed {
+        generation: u64,
+        message: String,
+    },
+}
+
+/// A command for the audio thread and where to deliver its response.
+pub struct AudioRequest {
+    /// Identifies the request in logs
+    pub id: u64,
+    pub command: AudioCommand,
+    /// Unset for notifications the audio thread sends itself
+    pub reply: Option<mpsc::Sender<AudioResponse>>,
+}
+
+impl From<AudioCommand> for AudioRequest {
+    fn from(command: AudioCommand) -> Self {
+        Self {
+            id: 0,
+            command,
+            reply: None,
+        }
+    }
+}
+
+/// Answers a single request on its own reply channel.
+#[derive(Clone)]
+struct Responder {
+    id: u64,
+    reply: Option<mpsc::Sender<AudioResponse>>,
+}
+
+impl Responder {
+    fn send(&self, response: AudioResponse) {
+        let Some(reply) = &self.reply else {
+            return;
+        };
+        if reply.send(response).is_err() {
+            warn!(
+                "Dropping the reply to request {}, its caller stopped waiting",
+                self.id
+            );
+        }
+    }
+}
+
+/// Handle used to send requests to an audio thread.
+#[derive(Clone)]
+pub struct AudioThread {
+    commands: mpsc::Sender<AudioRequest>,
+    /// Set when the thread is replaced after hanging, so it exits instead of
+    /// running requests whose callers have given up
+    retired: Arc<AtomicBool>,
+}
+
+impl AudioThread {
+    pub fn send(&self, request: AudioRequest) -> Result<(), RecorderError> {
+        self.commands
+            .send(request)
+            .map_err(|e| RecorderError::SendError(e.to_string()))
+    }
+
+    /// Makes the thread exit once it gets to its next request. Capture
+    /// callbacks keep the request channel open, so a wake-up is sent too
+    /// rather than waiting for the channel to close.
+    pub fn retire(&self) {
+        self.retired.store(true, Ordering::Relaxed);
+        // Nobody waits for a reply, and a dead thread can't receive it anyway
+        let _ = self.commands.send(AudioCommand::CloseThread.into());
+    }
+
+    /// Whether both handles lead to the same thread.
+    pub fn is_same(&self, other: &AudioThread) -> bool {
+        Arc::ptr_eq(&self.retired, &other.retired)
+    }
 }
 
 #[derive(Debug)]
 pub enum AudioResponse {
-    RecordingDeviceList(Vec<String>),
-    AudioData(Vec<f32>),
-    Error(String),
+    RecordingDeviceList(Vec<DeviceInfo>),
+    DeviceCapabilities(DeviceCapabilities),
+    SessionInitialized(CaptureConfig),
+    DeviceTest(CaptureConfig, RecordedAudio, CaptureStats),
+    RecordingSaved(RecordingFile, CaptureStats),
+    Recordings(Vec<ActiveRecording>),
+    RecorderState(RecorderState),
+    InvalidTransition(InvalidTransition),
+    Error(AudioError),
     Success(String),
 }
 
 pub struct RecordingSession {
+    device_id: String,
+    /// Move to the system default input if the device fails mid-session
+    failover_to_default: bool,
+    /// Bumped every time the stream is replaced
+    generation: u64,
     stream: Stream,
     is_recording: Arc<AtomicBool>,
-    audio_buffer: Arc<Mutex<Vec<f32>>>,
+    /// Converted 16 kHz mono audio, filled by the capture consumer thread
+    audio: Arc<Mutex<SessionAudio>>,
+    consumer: CaptureConsumer,
+    counters: Arc<CaptureCounters>,
+    /// ID the frontend gave the current recording, used to name its file.
+    /// Empty while no recording is in progress.
+    recording_id: String,
+    paused: bool,
+    /// Set once the recording stopped on its own, after which it can't be resumed
+    auto_stopped: bool,
+    /// Buffer offsets, in samples, at which the recording was paused
+    segment_markers: Vec<usize>,
+    /// Silence handling for the current recording
+    silence: Option<SilenceConfig>,
+    limits: RecordingLimits,
 }
 
-pub fn spawn_audio_thread(
-    response_tx: mpsc::Sender<AudioResponse>,
-) -> Result<mpsc::Sender<AudioCommand>, SendError<AudioCommand>> {
-    let (tx, rx) = mpsc::channel();
-
-    std::thread::spawn(move || -> Result<(), SendError<AudioResponse>> {
-        let host = cpal::default_host();
-        let mut current_session: Option<RecordingSession> = None;
-
-        while let Ok(cmd) = rx.recv() {
-            match cmd {
-                AudioCommand::EnumerateRecordingDevices => {
-                    let devices = host
-                        .input_devices()
-                        .map(|devices| devices.filter_map(|d| d.name().ok()).collect())
-                        .unwrap_or_else(|e| {
-                            let _ = response_tx.send(AudioResponse::Error(e.to_string()));
-                            vec![]
-                        });
-                    response_tx.send(AudioResponse::RecordingDeviceList(devices))?;
+/// A recording in progress, as listed for the frontend.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveRecording {
+    pub recording_id: String,
+    pub device_id: String,
+    /// Seconds captured so far
+    pub duration_secs: f32,
+    /// Set while paused or after stopping on its own
+    pub paused: bool,
+    /// Started with `start_background_recording` rather than on the main session
+    pub background: bool,
+}
+
+impl ActiveRecording {
+    fn from_session(session: &RecordingSession, background: bool) -> Self {
+        let samples = session
+            .audio
+            .lock()
+            .map(|audio| audio.len())
+            .unwrap_or_default();
+        Self {
+            recording_id: session.recording_id.clone(),
+            device_id: session.device_id.clone(),
+            duration_secs: samples as f32 / TARGET_SAMPLE_RATE as f32,
+            paused: !session.is_recording.load(Ordering::Relaxed),
+            background,
+        }
+    }
+}
+
+/// How much buffer to pre-allocate for a recording, never more than its limit.
+fn initial_capacity(limits: &RecordingLimits) -> usize {
+    limits
+        .max_samples()
+        .map_or(INITIAL_BUFFER_CAPACITY, |(max_samples, _)| {
+            max_samples.min(INITIAL_BUFFER_CAPACITY)
+        })
+}
+
+/// Validates `action` against the current recorder state. If it isn't allowed,
+/// the rejection is sent back to the caller and `None` is returned.
+fn check_transition(
+    state: &RecorderStateMachine,
+    action: RecorderAction,
+    responder: &Responder,
+) -> Option<RecorderState> {
+    match state.next(action) {
+        Ok(next) => Some(next),
+        Err(e) => {
+            responder.send(AudioResponse::InvalidTransition(e));
+            None
+        }
+    }
+}
+
+/// What every capture opened by the audio thread reports back through.
+#[derive(Clone)]
+struct CaptureContext {
+    app: AppHandle,
+    /// Lets stream error callbacks and the capture consumer report back to the audio thread
+    command_tx: mpsc::Sender<AudioRequest>,
+}
+
+/// Opens an input stream on `device` that feeds the shared session audio.
+///
+/// Stream errors are reported back to the audio thread as
+/// [`AudioCommand::StreamFailed`] tagged with `generation`, so errors from a
+/// stream that has since been replaced can be ignored.
+fn open_capture(
+    context: &CaptureContext,
+    device: &Device,
+    requested_config: Option<&RequestedConfig>,
+    generation: u64,
+    is_recording: &Arc<AtomicBool>,
+    audio: &Arc<Mutex<SessionAudio>>,
+    counters: &Arc<CaptureCounters>,
+) -> Result<(Stream, CaptureConsumer, CaptureConfig), AudioError> {
+    let config = select_input_config(device, requested_config)?;
+    let mut capture_config = CaptureConfig::from(&config);
+    let input_channel = requested_config.and_then(|requested| requested.input_channel);
+    capture_config.input_channel = input_channel.filter(|&channel| channel < config.channels());
+    if input_channel.is_some() && capture_config.input_channel.is_none() {
+        warn!(
+            "Input channel {:?} is not available with {} channels, averaging them instead",
+            input_channel,
+            config.channels()
+        );
+    }
+
+    // The input callback only copies raw samples into a lock-free ring buffer.
+    // A consumer thread downmixes and resamples them to 16kHz mono.
+    let (producer, consumer) = capture_pipeline(
+        context.app.clone(),
+        config.sample_rate().0,
+        config.channels(),
+        capture_config.input_channel,
+        audio.clone(),
+        counters.clone(),
+    )
+    .map_err(|e| AudioError::CaptureThreadFailed(e.to_string()))?;
+
+    let stream_config: StreamConfig = config.clone().into();
+    macro_rules! build {
+        ($sample:ty) => {
+            build_input_stream::<$sample>(
+                device,
+                &stream_config,
+                producer,
+                is_recording,
+                generation,
+                &context.command_tx,
+            )
+        };
+    }
+    let stream = match config.sample_format() {
+        SampleFormat::F32 => build!(f32),
+        SampleFormat::F64 => build!(f64),
+        SampleFormat::I8 => build!(i8),
+        SampleFormat::I16 => build!(i16),
+        SampleFormat::I32 => build!(i32),
+        SampleFormat::I64 => build!(i64),
+        SampleFormat::U8 => build!(u8),
+        SampleFormat::U16 => build!(u16),
+        SampleFormat::U32 => build!(u32),
+        SampleFormat::U64 => build!(u64),
+        format => Err(AudioError::UnsupportedConfig(format!(
+            "Unsupported sample format: {}",
+            format
+        ))),
+    }?;
+
+    Ok((stream, consumer, capture_config))
+}
+
+/// Builds an input stream delivering `T` samples, converting them to f32 as
+/// they're pushed into the capture ring buffer.
+fn build_input_stream<T>(
+    device: &Device,
+    config: &StreamConfig,
+    mut producer: CaptureProducer,
+    is_recording: &Arc<AtomicBool>,
+    generation: u64,
+    command_tx: &mpsc::Sender<AudioRequest>,
+) -> Result<Stream, AudioError>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let is_recording_producer = is_recording.clone();
+    let error_tx = command_tx.clone();
+    let stream = device.build_input_stream(
+        config,
+        move |data: &[T], _: &_| {
+            if is_recording_producer.load(Ordering::Relaxed) {
+                producer.push(data);
+            }
+        },
+        move |err| {
+            error!("Error in stream: {}", err);
+            let _ = error_tx.send(
+                AudioCommand::StreamFailed {
+                    generation,
+                    message: err.to_string(),
                 }
+                .into(),
+            );
+        },
+        None,
+    )?;
+    Ok(stream)
+}
 
-                AudioCommand::InitRecordingSession(device_name) => {
-                    // Create a new pre-allocated buffer for storing audio data
-                    let audio_buffer =
-                        Arc::new(Mutex::new(Vec::with_capacity(INITIAL_BUFFER_CAPACITY)));
-                    let is_recording: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
-                    let is_recording_producer = is_recording.clone();
-                    let buffer_clone = audio_buffer.clone();
-
-                    let device = match host.input_devices() {
-                        Ok(mut devices) => {
-                            match devices
-                                .find(|d| matches!(d.name(), Ok(name) if name == device_name))
-                            {
-                                Some(device) => device,
-                                None => {
-                                    response_tx.send(AudioResponse::Error(
-                                        "Device not found".to_string(),
-                                    ))?;
-                                    continue;
-                                }
+/// Saves a stopped recording cut down to its first `end` samples, trimming
+/// silence if `trim` is given, and returns its path along with the part of
+/// the captured audio that was kept. A recording that was written to disk
+/// while capturing is cut from the file instead of being loaded into memory.
+fn save_recording(
+    app: &AppHandle,
+    recording_id: &str,
+    audio: FinishedAudio,
+    end: usize,
+    trim: Option<&SilenceConfig>,
+) -> Result<(PathBuf, Range<usize>), AudioError> {
+    let failed = |e: hound::Error| AudioError::RecordingFileFailed(e.to_string());
+    match audio {
+        FinishedAudio::File { path, len } => {
+            let end = end.min(len);
+            let keep = match trim {
+                Some(silence) => {
+                    let mut scan = SilenceScan::new(silence);
+                    read_wav_chunks(&path, end, |chunk| scan.push(chunk)).map_err(failed)?;
+                    scan.finish().unwrap_or(end..end)
+                }
+                None => 0..end,
+            };
+            if keep != (0..len) {
+                cut_wav_file(&path, keep.clone()).map_err(failed)?;
+            }
+            Ok((path, keep))
+        }
+        FinishedAudio::Memory(mut samples) => {
+            samples.truncate(end);
+            let trimmed_start = match trim {
+                Some(silence) => trim_silence(&mut samples, silence),
+                None => 0,
+            };
+            let keep = trimmed_start..trimmed_start + samples.len();
+            let dir =
+                recordings_dir(app).map_err(|e| AudioError::RecordingFileFailed(e.to_string()))?;
+            let path = recording_path(&dir, recording_id);
+            let audio = RecordedAudio {
+                samples,
+                sample_rate: TARGET_SAMPLE_RATE,
+                channels: TARGET_CHANNELS,
+            };
+            write_wav_file(&path, &audio).map_err(failed)?;
+            Ok((path, keep))
+        }
+    }
+}
+
+/// Opens `device_id` the same way a recording session does, captures for
+/// `seconds` and returns what was recorded.
+fn test_device(
+    context: &CaptureContext,
+    host: &Host,
+    device_id: &str,
+    seconds: f32,
+    requested_config: Option<&RequestedConfig>,
+) -> Result<(CaptureConfig, RecordedAudio, CaptureStats), AudioError> {
+    let device = find_input_device(host, device_id)?;
+    let is_recording = Arc::new(AtomicBool::new(true));
+    let audio = Arc::new(Mutex::new(SessionAudio::default()));
+    let counters = Arc::new(CaptureCounters::default());
+
+    let (stream, consumer, config) = open_capture(
+        context,
+        &device,
+        requested_config,
+        TEST_GENERATION,
+        &is_recording,
+        &audio,
+        &counters,
+    )?;
+    stream.play()?;
+    std::thread::sleep(Duration::from_secs_f32(
+        seconds.clamp(0.1, MAX_TEST_SECONDS),
+    ));
+    is_recording.store(false, Ordering::Relaxed);
+    drop(stream);
+    consumer.flush();
+
+    let samples = audio
+        .lock()
+        .map(|mut audio| audio.take_samples())
+        .unwrap_or_default();
+    Ok((
+        config,
+        RecordedAudio {
+            samples,
+            sample_rate: TARGET_SAMPLE_RATE,
+            channels: TARGET_CHANNELS,
+        },
+        counters.snapshot(),
+    ))
+}
+
+/// Device tests still capturing, with the device each one opened.
+type DeviceTests = Vec<(String, JoinHandle<()>)>;
+
+/// Whether a device test is still capturing from `device_id`.
+fn is_testing(device_tests: &mut DeviceTests, device_id: &str) -> bool {
+    device_tests.retain(|(_, handle)| !handle.is_finished());
+    device_tests.iter().any(|(tested, _)| tested == device_id)
+}
+
+/// Opens `device_id` and sets up an idle session on it.
+fn open_session(
+    context: &CaptureContext,
+    host: &Host,
+    device_id: String,
+    requested_config: Option<&RequestedConfig>,
+    failover_to_default: bool,
+    limits: RecordingLimits,
+    generation: u64,
+) -> Result<(RecordingSession, CaptureConfig), AudioError> {
+    // Create a new pre-allocated buffer for storing audio data
+    let audio = Arc::new(Mutex::new(SessionAudio::with_capacity(initial_capacity(
+        &limits,
+    ))));
+    let is_recording: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+    let counters = Arc::new(CaptureCounters::default());
+
+    let device = find_input_device(host, &device_id)?;
+    let (stream, consumer, config) = open_capture(
+        context,
+        &device,
+        requested_config,
+        generation,
+        &is_recording,
+        &audio,
+        &counters,
+    )?;
+
+    let session = RecordingSession {
+        device_id,
+        failover_to_default,
+        generation,
+        stream,
+        is_recording,
+        audio,
+        consumer,
+        counters,
+        recording_id: String::new(),
+        paused: false,
+        auto_stopped: false,
+        segment_markers: Vec::new(),
+        silence: None,
+        limits,
+    };
+    Ok((session, config))
+}
+
+/// Starts a new recording on `session`, optionally streaming audio chunks to
+/// the frontend as they're captured and splitting them into speech segments.
+fn start_session_recording(
+    context: &CaptureContext,
+    session: &mut RecordingSession,
+    recording_id: String,
+    stream_chunks: bool,
+    vad: Option<VadConfig>,
+    silence: Option<SilenceConfig>,
+) -> Result<(), AudioError> {
+    let app = &context.app;
+    session.counters.reset();
+    session.consumer.set_recording_id(recording_id.clone());
+    if stream_chunks {
+        session
+            .consumer
+            .start_streaming(ChunkStream::new(app.clone()));
+    }
+    if let Some(vad) = vad {
+        session.consumer.start_vad(VadStream::new(app.clone(), vad));
+    }
+    let timeout_tx = context.command_tx.clone();
+    let timeout_id = recording_id.clone();
+    let watch = silence.as_ref().and_then(|silence| {
+        SilenceWatch::new(silence, move |silence_secs| {
+            let _ = timeout_tx.send(
+                AudioCommand::AutoStop {
+                    recording_id: timeout_id,
+                    reason: RecordingAutoStopped::Silence { silence_secs },
+                }
+                .into(),
+            );
+        })
+    });
+    if let Some(watch) = watch {
+        session.consumer.watch_silence(watch);
+    }
+    if let Some((max_samples, kind)) = session.limits.max_samples() {
+        let reason = match kind {
+            LimitKind::Duration => RecordingAutoStopped::DurationLimit {
+                max_duration_secs: max_samples as f32 / TARGET_SAMPLE_RATE as f32,
+            },
+            LimitKind::Bytes => RecordingAutoStopped::MemoryLimit {
+                max_bytes: session.limits.max_bytes.unwrap_or_default(),
+            },
+        };
+        let limit_tx = context.command_tx.clone();
+        let limit_id = recording_id.clone();
+        session
+            .consumer
+            .watch_limit(LimitWatch::new(max_samples, move || {
+                let _ = limit_tx.send(
+                    AudioCommand::AutoStop {
+                        recording_id: limit_id,
+                        reason,
+                    }
+                    .into(),
+                );
+            }));
+    }
+    let spill = recordings_dir(app)
+        .map_err(hound::Error::from)
+        .and_then(|dir| SpillWriter::create(&dir, &recording_id));
+    let spill = spill
+        .inspect_err(|e| {
+            warn!(
+                "Keeping recording {} in memory only, failed to create its file: {}",
+                recording_id, e
+            )
+        })
+        .ok();
+    // Clear any existing data when starting a new recording
+    if let Ok(mut audio) = session.audio.lock() {
+        audio.start(spill, initial_capacity(&session.limits));
+    }
+    session.recording_id = recording_id;
+    session.paused = false;
+    session.auto_stopped = false;
+    session.segment_markers.clear();
+    session.silence = silence;
+
+    session.is_recording.store(true, Ordering::Relaxed);
+    if let Err(e) = session.stream.play() {
+        session.is_recording.store(false, Ordering::Relaxed);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// Stops the recording on `session` and saves it, leaving the session idle.
+fn stop_session_recording(
+    app: &AppHandle,
+    session: &mut RecordingSession,
+) -> Result<RecordingFile, AudioError> {
+    // First stop recording to prevent new data from coming in
+    session.is_recording.store(false, Ordering::Relaxed);
+
+    // Stop the stream
+    session.stream.pause().unwrap_or_default();
+
+    // Wait for the consumer to convert whatever is still in the ring buffer
+    session.consumer.flush();
+    session.consumer.finish();
+
+    // Move the recorded audio out rather than doubling memory with a copy
+    let audio = session
+        .audio
+        .lock()
+        .map(|mut audio| std::mem::take(&mut *audio))
+        .unwrap_or_default();
+    let captured_len = audio.len();
+    // The session no longer has a recording once it's stopped, even if saving fails
+    let recording_id = std::mem::take(&mut session.recording_id);
+    let audio = audio
+        .finish()
+        .map_err(|e| AudioError::RecordingFileFailed(e.to_string()))?;
+    // Capture stops a few milliseconds after the limit is hit
+    let end = session
+        .limits
+        .max_samples()
+        .map_or(captured_len, |(max_samples, _)| {
+            max_samples.min(captured_len)
+        });
+    let trim = session.silence.as_ref().filter(|silence| silence.trim);
+    let (path, kept) = save_recording(app, &recording_id, audio, end, trim)?;
+
+    // Shift the markers to match the trimmed audio
+    let segment_markers = session
+        .segment_markers
+        .iter()
+        .filter_map(|marker| marker.checked_sub(kept.start))
+        .filter(|marker| *marker <= kept.len())
+        .map(|marker| marker as f32 / TARGET_SAMPLE_RATE as f32)
+        .collect();
+
+    debug!("Recorded {} samples total", kept.len());
+    Ok(RecordingFile {
+        recording_id,
+        path,
+        duration_secs: kept.len() as f32 / TARGET_SAMPLE_RATE as f32,
+        segment_markers,
+    })
+}
+
+/// Moves a session whose device failed over to the system default input,
+/// keeping the audio captured so far. Returns the new device's name.
+fn fail_over_to_default(
+    context: &CaptureContext,
+    host: &Host,
+    session: &mut RecordingSession,
+    generation: u64,
+) -> Result<String, AudioError> {
+    let device = find_input_device(host, DEFAULT_DEVICE_ID)?;
+    let name = device
+        .name()
+        .unwrap_or_else(|_| DEFAULT_DEVICE_ID.to_string());
+
+    let (stream, consumer, config) = open_capture(
+        context,
+        &device,
+        None,
+        generation,
+        &session.is_recording,
+        &session.audio,
+        &session.counters,
+    )?;
+    if session.is_recording.load(Ordering::Relaxed) {
+        stream.play()?;
+    }
+    consumer.restore_outputs(session.consumer.take_outputs());
+
+    session.generation = generation;
+    session.stream = stream;
+    session.consumer = consumer;
+    session.device_id = DEFAULT_DEVICE_ID.to_string();
+    info!(
+        "Reopened capture on {} at {} Hz, {} channels",
+        name, config.sample_rate, config.channels
+    );
+    Ok(name)
+}
+
+/// Spawns the audio thread. The returned join handle lets a supervisor notice
+/// when it dies.
+pub fn spawn_audio_thread(app: AppHandle) -> std::io::Result<(AudioThread, JoinHandle<()>)> {
+    let (tx, rx) = mpsc::channel::<AudioRequest>();
+    let retired = Arc::new(AtomicBool::new(false));
+    let context = CaptureContext {
+        app: app.clone(),
+        command_tx: tx.clone(),
+    };
+
+    let thread_retired = retired.clone();
+    let handle = std::thread::Builder::new()
+        .name("audio-thread".to_string())
+        .spawn(move || {
+            let host = cpal::default_host();
+            let mut current_session: Option<RecordingSession> = None;
+            let mut background: HashMap<String, RecordingSession> = HashMap::new();
+            let mut device_tests = DeviceTests::new();
+            // Stream generations handed out so far, shared by every session so a
+            // stream error always identifies a single session
+            let mut generations: u64 = 0;
+            let mut state = RecorderStateMachine::new(app.clone());
+
+            while let Ok(request) = rx.recv() {
+                if thread_retired.load(Ordering::Relaxed) {
+                    // Callers of anything still queued have given up on this thread
+                    warn!("Retired audio thread exiting");
+                    break;
+                }
+                let responder = Responder {
+                    id: request.id,
+                    reply: request.reply,
+                };
+                match request.command {
+                    AudioCommand::GetRecorderState => {
+                        responder.send(AudioResponse::RecorderState(state.state()));
+                    }
+
+                    AudioCommand::EnumerateRecordingDevices => match list_input_devices(&host) {
+                        Ok(devices) => {
+                            responder.send(AudioResponse::RecordingDeviceList(devices));
+                        }
+                        Err(e) => responder.send(AudioResponse::Error(e)),
+                    },
+
+                    AudioCommand::GetDeviceCapabilities(device_id) => {
+                        let capabilities = find_input_device(&host, &device_id)
+                            .and_then(|device| device_capabilities(&device, device_id));
+                        match capabilities {
+                            Ok(capabilities) => {
+                                responder.send(AudioResponse::DeviceCapabilities(capabilities))
                             }
+                            Err(e) => responder.send(AudioResponse::Error(e)),
                         }
-                        Err(e) => {
-                            response_tx.send(AudioResponse::Error(e.to_string()))?;
+                    }
+
+                    AudioCommand::InitRecordingSession {
+                        device_id,
+                        requested_config,
+                        failover_to_default,
+                        limits,
+                    } => {
+                        let Some(next_state) =
+                            check_transition(&state, RecorderAction::InitSession, &responder)
+                        else {
+                            continue;
+                        };
+                        if is_testing(&mut device_tests, &device_id) {
+                            responder.send(AudioResponse::InvalidTransition(
+                                InvalidTransition::DeviceInUse,
+                            ));
                             continue;
                         }
-                    };
-
-                    // Config can sometimes be overkill (96kHz with 3 channels)
-                    // Attempt to create config with lower voice-friendly parameters, otherwise use default config
-                    let config = match device.supported_input_configs() {
-                        Ok(mut supported_configs) => {
-                            // Find a config with voice-friendly parameters
-                            let voice_config = supported_configs.find(|config| {
-                                let min_rate = config.min_sample_rate().0;
-                                let max_rate = config.max_sample_rate().0;
-                                let channels = config.channels();
-
-                                // Look for mono or stereo config that supports common voice sample rates
-                                (channels == 1 || channels == 2)
-                                    && (min_rate..=max_rate).contains(&16000)
-                            });
 
-                            match voice_config {
-                                Some(config) => {
-                                    // Use 16kHz for voice and prefer mono if available
-                                    let config = config.with_sample_rate(cpal::SampleRate(16000));
-                                    println!(
-                                        "Using voice-optimized config: {} Hz, {} channels",
-                                        config.sample_rate().0,
-                                        config.channels()
-                                    );
-                                    config
-                                }
-                                None => {
-                                    // If no voice-friendly config found, try to get default and optimize it
-                                    let default_config = match device.default_input_config() {
-                                        Ok(config) => config,
-                                        Err(e) => {
-                                            response_tx.send(AudioResponse::Error(format!(
-                                                "Failed to get default input config: {}",
-                                                e
-                                            )))?;
-                                            continue;
-                                        }
-                                    };
-                                    println!(
-                                        "No voice-optimized config found. Default config: {} Hz, {} channels",
-                                        default_config.sample_rate().0,
-                                        default_config.channels()
-                                    );
-                                    default_config
-                                }
+                        generations += 1;
+                        let opened = open_session(
+                            &context,
+                            &host,
+                            device_id,
+                            requested_config.as_ref(),
+                            failover_to_default,
+                            limits,
+                            generations,
+                        );
+                        let (session, config) = match opened {
+                            Ok(opened) => opened,
+                            Err(e) => {
+                                responder.send(AudioResponse::Error(e));
+                                continue;
+                            }
+                        };
+                        current_session = Some(session);
+                        state.set(next_state);
+
+                        responder.send(AudioResponse::SessionInitialized(config));
+                    }
+
+                    AudioCommand::StartRecording {
+                        recording_id,
+                        stream_chunks,
+                        vad,
+                        silence,
+                    } => {
+                        let Some(next_state) =
+                            check_transition(&state, RecorderAction::StartRecording, &responder)
+                        else {
+                            continue;
+                        };
+
+                        if background.contains_key(&recording_id) {
+                            responder.send(AudioResponse::InvalidTransition(
+                                InvalidTransition::DuplicateRecording,
+                            ));
+                            continue;
+                        }
+                        let Some(session) = current_session.as_mut() else {
+                            responder.send(AudioResponse::InvalidTransition(
+                                InvalidTransition::NoSession,
+                            ));
+                            continue;
+                        };
+
+                        match start_session_recording(
+                            &context,
+                            session,
+                            recording_id,
+                            stream_chunks,
+                            vad,
+                            silence,
+                        ) {
+                            Ok(()) => {
+                                state.set(next_state);
+                                responder
+                                    .send(AudioResponse::Success("Recording started".to_string()));
                             }
+                            Err(e) => responder.send(AudioResponse::Error(e)),
                         }
-                        Err(e) => {
-                            response_tx.send(AudioResponse::Error(e.to_string()))?;
+                    }
+
+                    AudioCommand::StopRecording => {
+                        let Some(next_state) =
+                            check_transition(&state, RecorderAction::StopRecording, &responder)
+                        else {
                             continue;
+                        };
+                        let Some(session) = current_session.as_mut() else {
+                            responder.send(AudioResponse::InvalidTransition(
+                                InvalidTransition::NoSession,
+                            ));
+                            continue;
+                        };
+
+                        let saved = stop_session_recording(&app, session);
+                        state.set(next_state);
+                        match saved {
+                            Ok(file) => responder.send(AudioResponse::RecordingSaved(
+                                file,
+                                session.counters.snapshot(),
+                            )),
+                            Err(e) => responder.send(AudioResponse::Error(e)),
                         }
-                    };
-
-                    let sample_rate = config.sample_rate().0;
-                    let stream = match device.build_input_stream(
-                        &config.into(),
-                        move |data: &[f32], _: &_| {
-                            if is_recording_producer.load(Ordering::Relaxed) {
-                                if let Ok(mut buffer) = buffer_clone.lock() {
-                                    // Extend the buffer with new samples
-                                    buffer.extend_from_slice(data);
-                                    println!(
-                                        "Recorded {} samples, total length: {} ({:.2} seconds)",
-                                        data.len(),
-                                        buffer.len(),
-                                        buffer.len() as f32 / sample_rate as f32
-                                    );
-                                }
+                    }
+
+                    AudioCommand::StartBackgroundRecording {
+                        recording_id,
+                        device_id,
+                        requested_config,
+                        failover_to_default,
+                        limits,
+                        silence,
+                    } => {
+                        let is_primary = current_session
+                            .as_ref()
+                            .is_some_and(|session| session.recording_id == recording_id);
+                        if is_primary || background.contains_key(&recording_id) {
+                            responder.send(AudioResponse::InvalidTransition(
+                                InvalidTransition::DuplicateRecording,
+                            ));
+                            continue;
+                        }
+                        if is_testing(&mut device_tests, &device_id) {
+                            responder.send(AudioResponse::InvalidTransition(
+                                InvalidTransition::DeviceInUse,
+                            ));
+                            continue;
+                        }
+
+                        generations += 1;
+                        let opened = open_session(
+                            &context,
+                            &host,
+                            device_id,
+                            requested_config.as_ref(),
+                            failover_to_default,
+                            limits,
+                            generations,
+                        );
+                        let (mut session, config) = match opened {
+                            Ok(opened) => opened,
+                            Err(e) => {
+                                responder.send(AudioResponse::Error(e));
+                                continue;
                             }
-                        },
-                        |err| eprintln!("Error in stream: {}", err),
-                        None,
-                    ) {
-                        Ok(stream) => stream,
-                        Err(e) => {
-                            response_tx.send(AudioResponse::Error(format!(
-                                "Failed to build stream: {}",
-                                e
-                            )))?;
+                        };
+                        if let Err(e) = start_session_recording(
+                            &context,
+                            &mut session,
+                            recording_id.clone(),
+                            false,
+                            None,
+                            silence,
+                        ) {
+                            responder.send(AudioResponse::Error(e));
                             continue;
                         }
-                    };
+                        info!(
+                            "Started background recording {} on {}",
+                            recording_id, session.device_id
+                        );
+                        background.insert(recording_id, session);
+                        responder.send(AudioResponse::SessionInitialized(config));
+                    }
 
-                    current_session = Some(RecordingSession {
-                        stream,
-                        is_recording,
-                        audio_buffer,
-                    });
+                    AudioCommand::ListRecordings => {
+                        let primary = current_session
+                            .iter()
+                            .filter(|session| !session.recording_id.is_empty())
+                            .map(|session| ActiveRecording::from_session(session, false));
+                        let recordings = primary
+                            .chain(
+                                background
+                                    .values()
+                                    .map(|session| ActiveRecording::from_session(session, true)),
+                            )
+                            .collect();
+                        responder.send(AudioResponse::Recordings(recordings));
+                    }
 
-                    response_tx.send(AudioResponse::Success(
-                        "Recording session initialized".to_string(),
-                    ))?;
-                }
+                    AudioCommand::StopRecordingById(recording_id) => {
+                        let is_primary = current_session
+                            .as_ref()
+                            .is_some_and(|session| session.recording_id == recording_id);
+                        let saved = if is_primary {
+                            let Some(next_state) =
+                                check_transition(&state, RecorderAction::StopRecording, &responder)
+                            else {
+                                continue;
+                            };
+                            let Some(session) = current_session.as_mut() else {
+                                continue;
+                            };
+                            let saved = stop_session_recording(&app, session)
+                                .map(|file| (file, session.counters.snapshot()));
+                            state.set(next_state);
+                            saved
+                        } else if let Some(mut session) = background.remove(&recording_id) {
+                            // Dropping the session afterwards closes its stream
+                            stop_session_recording(&app, &mut session)
+                                .map(|file| (file, session.counters.snapshot()))
+                        } else {
+                            responder.send(AudioResponse::InvalidTransition(
+                                InvalidTransition::RecordingNotFound,
+                            ));
+                            continue;
+                        };
+                        match saved {
+                            Ok((file, stats)) => {
+                                responder.send(AudioResponse::RecordingSaved(file, stats))
+                            }
+                            Err(e) => responder.send(AudioResponse::Error(e)),
+                        }
+                    }
 
-                AudioCommand::StartRecording => {
-                    if let Some(session) = &current_session {
-                        // Clear any existing data when starting a new recording
-                        if let Ok(mut buffer) = session.audio_buffer.lock() {
-                            buffer.clear();
+                    AudioCommand::CancelRecording => {
+                        let Some(next_state) =
+                            check_transition(&state, RecorderAction::CancelRecording, &responder)
+                        else {
+                            continue;
+                        };
+                        let Some(session) = current_session.as_mut() else {
+                            responder.send(AudioResponse::InvalidTransition(
+                                InvalidTransition::NoSession,
+                            ));
+                            continue;
+                        };
+
+                        session.is_recording.store(false, Ordering::Relaxed);
+                        session.stream.pause().unwrap_or_default();
+                        session.consumer.discard();
+                        if let Ok(mut audio) = session.audio.lock() {
+                            audio.discard();
+                        }
+                        session.recording_id.clear();
+                        session.paused = false;
+                        session.auto_stopped = false;
+                        session.segment_markers.clear();
+                        state.set(next_state);
+                        responder.send(AudioResponse::Success("Recording canceled".to_string()));
+                    }
+
+                    AudioCommand::PauseRecording { mark_segment } => {
+                        if check_transition(&state, RecorderAction::PauseRecording, &responder)
+                            .is_none()
+                        {
+                            continue;
+                        }
+                        let Some(session) = current_session.as_mut() else {
+                            responder.send(AudioResponse::InvalidTransition(
+                                InvalidTransition::NoSession,
+                            ));
+                            continue;
+                        };
+                        if session.paused {
+                            responder.send(AudioResponse::InvalidTransition(
+                                InvalidTransition::AlreadyPaused,
+                            ));
+                            continue;
+                        }
+
+                        session.is_recording.store(false, Ordering::Relaxed);
+                        session.stream.pause().unwrap_or_default();
+                        session.paused = true;
+                        if mark_segment {
+                            // Make sure the marker lands after everything captured so far
+                            session.consumer.flush();
+                            let offset = session
+                                .audio
+                                .lock()
+                                .map(|audio| audio.len())
+                                .unwrap_or_default();
+                            session.segment_markers.push(offset);
+                        }
+                        responder.send(AudioResponse::Success("Recording paused".to_string()));
+                    }
+
+                    AudioCommand::ResumeRecording => {
+                        if check_transition(&state, RecorderAction::ResumeRecording, &responder)
+                            .is_none()
+                        {
+                            continue;
+                        }
+                        let Some(session) = current_session.as_mut() else {
+                            responder.send(AudioResponse::InvalidTransition(
+                                InvalidTransition::NoSession,
+                            ));
+                            continue;
+                        };
+                        // Capturing more would only be cut off when it's saved
+                        if session.auto_stopped {
+                            responder.send(AudioResponse::InvalidTransition(
+                                InvalidTransition::AutoStopped,
+                            ));
+                            continue;
+                        }
+                        if !session.paused {
+                            responder.send(AudioResponse::InvalidTransition(
+                                InvalidTransition::NotPaused,
+                            ));
+                            continue;
                         }
 
                         session.is_recording.store(true, Ordering::Relaxed);
                         if let Err(e) = session.stream.play() {
-                            response_tx.send(AudioResponse::Error(format!(
-                                "Failed to start stream: {}",
-                                e
-                            )))?;
+                            session.is_recording.store(false, Ordering::Relaxed);
+                            responder.send(AudioResponse::Error(e.into()));
                             continue;
                         }
-                        response_tx
-                            .send(AudioResponse::Success("Recording started".to_string()))?;
-                    } else {
-                        response_tx.send(AudioResponse::Error(
-                            "Recording session not initialized".to_string(),
-                        ))?;
+                        session.paused = false;
+                        responder.send(AudioResponse::Success("Recording resumed".to_string()));
                     }
-                }
 
-                AudioCommand::StopRecording => {
-                    if let Some(session) = &current_session {
-                        // First stop recording to prevent new data from coming in
-                        session.is_recording.store(false, Ordering::Relaxed);
+                    AudioCommand::CloseRecordingSession => {
+                        let Some(next_state) =
+                            check_transition(&state, RecorderAction::CloseSession, &responder)
+                        else {
+                            continue;
+                        };
 
-                        // Get a copy of all recorded audio data
-                        let audio_data = if let Ok(buffer) = session.audio_buffer.lock() {
-                            buffer.clone()
+                        if let Some(session) = current_session.take() {
+                            session.is_recording.store(false, Ordering::Relaxed);
+                            drop(session.stream);
+                            state.set(next_state);
+                            responder.send(AudioResponse::Success(
+                                "Recording session closed".to_string(),
+                            ));
                         } else {
-                            Vec::new()
-                        };
+                            responder.send(AudioResponse::Success(
+                                "No active recording session".to_string(),
+                            ));
+                        }
+                    }
 
-                        println!("Recorded {} samples total", audio_data.len());
+                    AudioCommand::AutoStop {
+                        recording_id,
+                        reason,
+                    } => {
+                        let session = match current_session.as_mut() {
+                            Some(session)
+                                if session.recording_id == recording_id
+                                    && state.state() == RecorderState::SessionRecording =>
+                            {
+                                session
+                            }
+                            _ => match background.get_mut(&recording_id) {
+                                Some(session) => session,
+                                None => continue,
+                            },
+                        };
+                        if !session.is_recording.load(Ordering::Relaxed) {
+                            // A limit reached just before pausing still applies
+                            if !matches!(reason, RecordingAutoStopped::Silence { .. }) {
+                                session.auto_stopped = true;
+                            }
+                            continue;
+                        }
+                        match reason {
+                            RecordingAutoStopped::Silence { silence_secs } => {
+                                info!(
+                                    "Auto-stopping {} after {:.1}s of silence",
+                                    recording_id, silence_secs
+                                )
+                            }
+                            _ => warn!(
+                                "Auto-stopping {} at the session limit: {:?}",
+                                recording_id, reason
+                            ),
+                        }
 
-                        // Stop the stream
+                        // Stop capturing but leave the audio for stop_recording to collect
+                        session.is_recording.store(false, Ordering::Relaxed);
                         session.stream.pause().unwrap_or_default();
+                        session.auto_stopped = true;
+                        emit(
+                            &app,
+                            RECORDING_AUTO_STOPPED_EVENT,
+                            AutoStopped {
+                                recording_id,
+                                reason,
+                            },
+                        );
+                    }
+
+                    AudioCommand::TestRecordingDevice {
+                        device_id,
+                        seconds,
+                        requested_config,
+                    } => {
+                        // The session's device may be the one under test
+                        if state.state() == RecorderState::SessionRecording {
+                            responder.send(AudioResponse::InvalidTransition(
+                                InvalidTransition::AlreadyRecording,
+                            ));
+                            continue;
+                        }
+                        // Never open a second stream on a device that is already open
+                        let in_use = current_session
+                            .iter()
+                            .chain(background.values())
+                            .any(|session| session.device_id == device_id);
+                        if in_use || is_testing(&mut device_tests, &device_id) {
+                            responder.send(AudioResponse::InvalidTransition(
+                                InvalidTransition::DeviceInUse,
+                            ));
+                            continue;
+                        }
 
-                        response_tx.send(AudioResponse::AudioData(audio_data))?;
-                    } else {
-                        response_tx
-                            .send(AudioResponse::Error("No active recording".to_string()))?;
+                        let test_context = context.clone();
+                        let test_device_id = device_id.clone();
+                        let test_responder = responder.clone();
+                        let spawned = std::thread::Builder::new()
+                            .name("audio-device-test".to_string())
+                            .spawn(move || {
+                                let host = cpal::default_host();
+                                match test_device(
+                                    &test_context,
+                                    &host,
+                                    &test_device_id,
+                                    seconds,
+                                    requested_config.as_ref(),
+                                ) {
+                                    Ok((config, audio, stats)) => test_responder
+                                        .send(AudioResponse::DeviceTest(config, audio, stats)),
+                                    Err(e) => test_responder.send(AudioResponse::Error(e)),
+                                }
+                            });
+                        match spawned {
+                            Ok(handle) => device_tests.push((device_id, handle)),
+                            Err(e) => responder.send(AudioResponse::Error(
+                                AudioError::CaptureThreadFailed(e.to_string()),
+                            )),
+                        }
                     }
-                }
 
-                AudioCommand::CloseRecordingSession => {
-                    if let Some(session) = current_session.take() {
-                        session.is_recording.store(false, Ordering::Relaxed);
-                        drop(session.stream);
-                        response_tx.send(AudioResponse::Success(
-                            "Recording session closed".to_string(),
-                        ))?;
-                    } else {
-                        response_tx.send(AudioResponse::Success(
-                            "No active recording session".to_string(),
-                        ))?;
+                    AudioCommand::StreamFailed {
+                        generation,
+                        message,
+                    } => {
+                        let session = match current_session.as_mut() {
+                            Some(session) if session.generation == generation => session,
+                            _ => match background
+                                .values_mut()
+                                .find(|session| session.generation == generation)
+                            {
+                                Some(session) => session,
+                                // Stale error from a stream that was already replaced
+                                None => continue,
+                            },
+                        };
+                        warn!("Input stream failed on {}: {}", session.device_id, message);
+
+                        // Keep everything captured before the failure
+                        session.consumer.flush();
+                        let kept_seconds = session
+                            .audio
+                            .lock()
+                            .map(|audio| audio.len() as f32 / TARGET_SAMPLE_RATE as f32)
+                            .unwrap_or_default();
+                        let lost_device_id = session.device_id.clone();
+                        let recording_id =
+                            Some(session.recording_id.clone()).filter(|id| !id.is_empty());
+
+                        let failed_over_to = if session.failover_to_default {
+                            generations += 1;
+                            match fail_over_to_default(&context, &host, session, generations) {
+                                Ok(name) => {
+                                    info!("Failed over to default input device: {}", name);
+                                    Some(name)
+                                }
+                                Err(e) => {
+                                    error!("Failed to fail over to default input device: {}", e);
+                                    None
+                                }
+                            }
+                        } else {
+                            None
+                        };
+
+                        emit(
+                            &app,
+                            DEVICE_LOST_EVENT,
+                            DeviceLost {
+                                device_id: lost_device_id,
+                                recording_id,
+                                message,
+                                kept_seconds,
+                                failed_over_to,
+                            },
+                        );
                     }
-                }
 
-                AudioCommand::CloseThread => {
-                    if let Some(session) = current_session.take() {
-                        session.is_recording.store(false, Ordering::Relaxed);
-                        drop(session.stream);
+                    AudioCommand::CloseThread => {
+                        if let Some(session) = current_session.take() {
+                            session.is_recording.store(false, Ordering::Relaxed);
+                            drop(session.stream);
+                        }
+                        // Unfinished background recordings are recovered on the next launch
+                        background.clear();
+                        state.set(RecorderState::Idle);
+                        responder.send(AudioResponse::Success("Thread closed".to_string()));
+                        break;
                     }
-                    response_tx.send(AudioResponse::Success("Thread closed".to_string()))?;
-                    break;
                 }
             }
-        }
-        Ok(())
-    });
+        })?;
 
-    Ok(tx)
+    let thread = AudioThread {
+        commands: tx,
+        retired,
+    };
+    Ok((thread, handle))
 }
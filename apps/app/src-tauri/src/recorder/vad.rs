@@ -0,0 +1,271 @@
+use super::convert::TARGET_SAMPLE_RATE;
+use super::level::rms;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use tracing::debug;
+
+/// Audio is classified in 30ms frames of 16kHz mono.
+const FRAME_SAMPLES: usize = 480;
+
+/// Tuning for the energy/zero-crossing voice activity detector. Fields left
+/// out by the frontend keep their defaults.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct VadConfig {
+    /// Minimum RMS level (0.0 to 1.0) for a frame to count as speech
+    pub energy_threshold: f32,
+    /// Frames crossing zero more often than this are treated as noise
+    pub max_zero_crossing_rate: f32,
+    /// Speech shorter than this is discarded as a misfire
+    pub min_speech_ms: u32,
+    /// Silence needed before a speech segment ends
+    pub hangover_ms: u32,
+    /// Audio from just before speech was detected that is kept in the segment
+    pub pre_roll_ms: u32,
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            energy_threshold: 0.015,
+            max_zero_crossing_rate: 0.35,
+            min_speech_ms: 250,
+            hangover_ms: 700,
+            pre_roll_ms: 300,
+        }
+    }
+}
+
+fn ms_to_samples(ms: u32) -> usize {
+    TARGET_SAMPLE_RATE as usize * ms as usize / 1000
+}
+
+#[derive(Debug)]
+pub enum VadEvent {
+    /// `start` is the offset of the segment, in samples since recording started
+    SpeechStart {
+        start: usize,
+    },
+    SpeechEnd {
+        start: usize,
+        samples: Vec<f32>,
+    },
+}
+
+struct Segment {
+    start: usize,
+    samples: Vec<f32>,
+    voiced_samples: usize,
+    trailing_silence: usize,
+    confirmed: bool,
+}
+
+/// Splits a 16kHz mono stream into speech segments using frame energy and
+/// zero-crossing rate.
+pub struct VoiceActivityDetector {
+    config: VadConfig,
+    frame: Vec<f32>,
+    pre_roll: VecDeque<f32>,
+    segment: Option<Segment>,
+    /// Samples classified so far
+    position: usize,
+}
+
+impl VoiceActivityDetector {
+    pub fn new(config: VadConfig) -> Self {
+        Self {
+            config,
+            frame: Vec::with_capacity(FRAME_SAMPLES),
+            pre_roll: VecDeque::with_capacity(ms_to_samples(config.pre_roll_ms)),
+            segment: None,
+            position: 0,
+        }
+    }
+
+    pub fn process(&mut self, samples: &[f32], events: &mut Vec<VadEvent>) {
+        let mut remaining = samples;
+        while !remaining.is_empty() {
+            let take = (FRAME_SAMPLES - self.frame.len()).min(remaining.len());
+            self.frame.extend_from_slice(&remaining[..take]);
+            remaining = &remaining[take..];
+
+            if self.frame.len() == FRAME_SAMPLES {
+                let frame = std::mem::take(&mut self.frame);
+                self.process_frame(&frame, events);
+                self.frame = frame;
+                self.frame.clear();
+            }
+        }
+    }
+
+    /// Ends the current segment, if speech was confirmed, with whatever audio
+    /// hasn't filled a whole frame yet.
+    pub fn finish(&mut self, events: &mut Vec<VadEvent>) {
+        if let Some(mut segment) = self.segment.take() {
+            segment.samples.append(&mut self.frame);
+            Self::end_segment(segment, events);
+        }
+        self.frame.clear();
+        self.pre_roll.clear();
+    }
+
+    fn is_speech(&self, frame: &[f32]) -> bool {
+        let crossings = frame
+            .windows(2)
+            .filter(|pair| (pair[0] >= 0.0) != (pair[1] >= 0.0))
+            .count();
+        let zero_crossing_rate = crossings as f32 / (frame.len() - 1) as f32;
+
+        rms(frame) >= self.config.energy_threshold
+            && zero_crossing_rate <= self.config.max_zero_crossing_rate
+    }
+
+    fn process_frame(&mut self, frame: &[f32], events: &mut Vec<VadEvent>) {
+        let is_speech = self.is_speech(frame);
+        let frame_start = self.position;
+        self.position += frame.len();
+
+        let segment = match self.segment.as_mut() {
+            Some(segment) => {
+                segment.samples.extend_from_slice(frame);
+                if is_speech {
+                    segment.voiced_samples += frame.len();
+                    segment.trailing_silence = 0;
+                } else {
+                    segment.trailing_silence += frame.len();
+                }
+                segment
+            }
+            None if is_speech => {
+                let mut samples: Vec<f32> = self.pre_roll.drain(..).collect();
+                let start = frame_start - samples.len();
+                samples.extend_from_slice(frame);
+                self.segment.insert(Segment {
+                    start,
+                    samples,
+                    voiced_samples: frame.len(),
+                    trailing_silence: 0,
+                    confirmed: false,
+                })
+            }
+            None => {
+                self.pre_roll.extend(frame);
+                let excess = self
+                    .pre_roll
+                    .len()
+                    .saturating_sub(ms_to_samples(self.config.pre_roll_ms));
+                self.pre_roll.drain(..excess);
+                return;
+            }
+        };
+
+        if !segment.confirmed && segment.voiced_samples >= ms_to_samples(self.config.min_speech_ms)
+        {
+            segment.confirmed = true;
+            events.push(VadEvent::SpeechStart {
+                start: segment.start,
+            });
+        }
+
+        if segment.trailing_silence >= ms_to_samples(self.config.hangover_ms) {
+            if let Some(segment) = self.segment.take() {
+                Self::end_segment(segment, events);
+            }
+        }
+    }
+
+    fn end_segment(segment: Segment, events: &mut Vec<VadEvent>) {
+        if segment.confirmed {
+            events.push(VadEvent::SpeechEnd {
+                start: segment.start,
+                samples: segment.samples,
+            });
+        } else {
+            debug!(
+                "Discarding {}ms of speech as a VAD misfire",
+                segment.voiced_samples * 1000 / TARGET_SAMPLE_RATE as usize
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    fn silence(ms: u32) -> Vec<f32> {
+        vec![0.0; ms_to_samples(ms)]
+    }
+
+    fn speech(ms: u32) -> Vec<f32> {
+        (0..ms_to_samples(ms))
+            .map(|i| 0.3 * (TAU * 200.0 * i as f32 / TARGET_SAMPLE_RATE as f32).sin())
+            .collect()
+    }
+
+    fn detect(audio: &[f32]) -> Vec<VadEvent> {
+        let mut vad = VoiceActivityDetector::new(VadConfig::default());
+        let mut events = Vec::new();
+        // Feed packets that don't line up with frames
+        for packet in audio.chunks(1_000) {
+            vad.process(packet, &mut events);
+        }
+        events
+    }
+
+    #[test]
+    fn silence_has_no_speech() {
+        assert!(detect(&silence(2_000)).is_empty());
+    }
+
+    #[test]
+    fn speech_segment_includes_pre_roll_and_hangover() {
+        let audio = [silence(300), speech(960), silence(1_000)].concat();
+        let events = detect(&audio);
+        assert_eq!(events.len(), 2, "{:?}", events);
+        assert!(matches!(events[0], VadEvent::SpeechStart { start: 0 }));
+        let VadEvent::SpeechEnd { start, samples } = &events[1] else {
+            panic!("expected the segment to end, got {:?}", events[1]);
+        };
+        assert_eq!(*start, 0);
+        // Pre-roll, the speech itself and the hangover rounded up to whole frames
+        assert_eq!(samples.len(), 4_800 + 15_360 + 24 * FRAME_SAMPLES);
+    }
+
+    #[test]
+    fn speech_start_is_reported_once_min_speech_is_reached() {
+        let audio = [silence(600), speech(300)].concat();
+        let events = detect(&audio);
+        assert_eq!(events.len(), 1, "{:?}", events);
+        // The segment starts a full pre-roll before the speech
+        assert!(matches!(events[0], VadEvent::SpeechStart { start: 4_800 }));
+    }
+
+    #[test]
+    fn short_bursts_are_discarded() {
+        let audio = [speech(120), silence(1_000)].concat();
+        assert!(detect(&audio).is_empty());
+    }
+
+    #[test]
+    fn loud_noise_is_not_speech() {
+        let noise: Vec<f32> = [0.5, -0.5].repeat(ms_to_samples(1_000) / 2);
+        assert!(detect(&noise).is_empty());
+    }
+
+    #[test]
+    fn finish_ends_a_confirmed_segment() {
+        let mut vad = VoiceActivityDetector::new(VadConfig::default());
+        let mut events = Vec::new();
+        vad.process(&speech(500), &mut events);
+        vad.process(&[0.1; 100], &mut events);
+        vad.finish(&mut events);
+        assert_eq!(events.len(), 2, "{:?}", events);
+        let VadEvent::SpeechEnd { samples, .. } = &events[1] else {
+            panic!("expected the segment to end, got {:?}", events[1]);
+        };
+        // The partial frame is kept
+        assert_eq!(samples.len(), ms_to_samples(500) + 100);
+    }
+}
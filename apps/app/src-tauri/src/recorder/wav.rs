@@ -0,0 +1,204 @@
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+use std::ops::Range;
+use std::path::Path;
+
+/// Raw interleaved samples together with the format they were captured in.
+#[derive(Debug)]
+pub struct RecordedAudio {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+impl RecordedAudio {
+    pub fn duration_secs(&self) -> f32 {
+        if self.sample_rate == 0 || self.channels == 0 {
+            return 0.0;
+        }
+        self.samples.len() as f32 / (self.sample_rate as f32 * self.channels as f32)
+    }
+}
+
+pub fn wav_spec(sample_rate: u32, channels: u16) -> WavSpec {
+    WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    }
+}
+
+/// Encodes the recording as a 16-bit PCM WAV file held in memory. Half the
+/// size of the float files recordings are saved as, for when the audio leaves
+/// the recorder.
+pub fn encode_wav_pcm16(audio: &RecordedAudio) -> Result<Vec<u8>, hound::Error> {
+    let spec = WavSpec {
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+        ..wav_spec(audio.sample_rate, audio.channels)
+    };
+    let mut cursor = Cursor::new(Vec::with_capacity(44 + audio.samples.len() * 2));
+    {
+        let mut writer = WavWriter::new(&mut cursor, spec)?;
+        let mut samples = writer.get_i16_writer(audio.samples.len() as u32);
+        for &sample in &audio.samples {
+            samples.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16);
+        }
+        samples.flush()?;
+        writer.finalize()?;
+    }
+    Ok(cursor.into_inner())
+}
+
+/// Writes the recording to `path` as a 32-bit float WAV file.
+pub fn write_wav_file(path: &Path, audio: &RecordedAudio) -> Result<(), hound::Error> {
+    let mut writer = WavWriter::create(path, wav_spec(audio.sample_rate, audio.channels))?;
+    for &sample in &audio.samples {
+        writer.write_sample(sample)?;
+    }
+    writer.finalize()
+}
+
+/// Reads a 32-bit float WAV file written by [`write_wav_file`] or the
+/// recording spill writer.
+pub fn read_wav_file(path: &Path) -> Result<RecordedAudio, hound::Error> {
+    let reader = open_f32_wav(path)?;
+    let spec = reader.spec();
+    let samples = reader.into_samples::<f32>().collect::<Result<_, _>>()?;
+    Ok(RecordedAudio {
+        samples,
+        sample_rate: spec.sample_rate,
+        channels: spec.channels,
+    })
+}
+
+/// Reads a WAV file to send elsewhere as 16-bit PCM. Files already stored
+/// that way are returned byte for byte; recordings are re-encoded.
+pub fn read_wav_pcm16(path: &Path) -> Result<Vec<u8>, hound::Error> {
+    let spec = WavReader::open(path)?.spec();
+    if spec.sample_format == SampleFormat::Int && spec.bits_per_sample == 16 {
+        return Ok(std::fs::read(path)?);
+    }
+    encode_wav_pcm16(&read_wav_file(path)?)
+}
+
+/// Size of the packets [`read_wav_chunks`] hands out.
+const READ_CHUNK_SAMPLES: usize = 16_384;
+
+fn open_f32_wav(path: &Path) -> Result<WavReader<BufReader<File>>, hound::Error> {
+    let reader = WavReader::open(path)?;
+    let spec = reader.spec();
+    if spec.sample_format != SampleFormat::Float || spec.bits_per_sample != 32 {
+        return Err(hound::Error::Unsupported);
+    }
+    Ok(reader)
+}
+
+/// Streams up to `len` samples of a 32-bit float WAV file to `f`, a packet at
+/// a time, without loading the file whole.
+pub fn read_wav_chunks(
+    path: &Path,
+    len: usize,
+    mut f: impl FnMut(&[f32]),
+) -> Result<(), hound::Error> {
+    let mut reader = open_f32_wav(path)?;
+    let mut chunk = Vec::with_capacity(READ_CHUNK_SAMPLES);
+    for sample in reader.samples::<f32>().take(len) {
+        chunk.push(sample?);
+        if chunk.len() == READ_CHUNK_SAMPLES {
+            f(&chunk);
+            chunk.clear();
+        }
+    }
+    if !chunk.is_empty() {
+        f(&chunk);
+    }
+    Ok(())
+}
+
+/// Cuts a mono 32-bit float WAV file down to `range` of its samples, streaming
+/// through a temporary file next to it.
+pub fn cut_wav_file(path: &Path, range: Range<usize>) -> Result<(), hound::Error> {
+    let partial = path.with_extension("wav.part");
+    {
+        let mut reader = open_f32_wav(path)?;
+        let mut writer = WavWriter::create(&partial, reader.spec())?;
+        if !range.is_empty() {
+            reader.seek(range.start as u32)?;
+            for sample in reader.samples::<f32>().take(range.len()) {
+                writer.write_sample(sample?)?;
+            }
+        }
+        writer.finalize()?;
+    }
+    std::fs::rename(&partial, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pcm16_wav_is_half_the_size_and_decodes() {
+        let audio = RecordedAudio {
+            samples: vec![0.0, 0.5, -0.5, 1.5, -1.5],
+            sample_rate: 16_000,
+            channels: 1,
+        };
+        let wav = encode_wav_pcm16(&audio).unwrap();
+        assert_eq!(wav.len(), 44 + audio.samples.len() * 2);
+
+        let reader = WavReader::new(Cursor::new(wav)).unwrap();
+        assert_eq!(reader.spec().bits_per_sample, 16);
+        let samples: Vec<i16> = reader.into_samples().map(Result::unwrap).collect();
+        // Out of range samples are clipped rather than wrapped
+        assert_eq!(samples, vec![0, 16_384, -16_384, i16::MAX, -i16::MAX]);
+    }
+
+    fn mono(samples: Vec<f32>) -> RecordedAudio {
+        RecordedAudio {
+            samples,
+            sample_rate: 16_000,
+            channels: 1,
+        }
+    }
+
+    #[test]
+    fn pcm16_files_are_read_as_is() {
+        let path = std::env::temp_dir().join(format!("pcm16-{}.wav", std::process::id()));
+        let wav = encode_wav_pcm16(&mono(vec![0.25; 1_000])).unwrap();
+        std::fs::write(&path, &wav).unwrap();
+        let read = read_wav_pcm16(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read, wav);
+    }
+
+    #[test]
+    fn float_recordings_are_read_as_pcm16() {
+        let path = std::env::temp_dir().join(format!("float-{}.wav", std::process::id()));
+        let audio = mono(vec![0.5; 1_000]);
+        write_wav_file(&path, &audio).unwrap();
+        let read = read_wav_pcm16(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read, encode_wav_pcm16(&audio).unwrap());
+    }
+
+    #[test]
+    fn cut_keeps_only_the_range() {
+        let path = std::env::temp_dir().join(format!("cut-{}.wav", std::process::id()));
+        let samples: Vec<f32> = (0..50_000).map(|i| i as f32 / 50_000.0).collect();
+        write_wav_file(&path, &mono(samples.clone())).unwrap();
+
+        let mut read = Vec::new();
+        read_wav_chunks(&path, 40_000, |chunk| read.extend_from_slice(chunk)).unwrap();
+        assert_eq!(read, samples[..40_000]);
+
+        cut_wav_file(&path, 1_000..30_000).unwrap();
+        let cut = read_wav_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(cut.samples, samples[1_000..30_000]);
+    }
+}
@@ -0,0 +1,152 @@
+use super::error::{Result, TranscriptionError};
+use super::local;
+use super::models::{self, ModelInfo, ModelsDiskUsage};
+use super::openai::{self, OpenAiEndpoint};
+use super::options::TranscriptionOptions;
+use crate::recorder::convert::TARGET_SAMPLE_RATE;
+use crate::recorder::recovery::recovered_path;
+use crate::recorder::spill::{recording_path, recordings_dir};
+use crate::recorder::wav::{read_wav_file, read_wav_pcm16};
+use std::path::PathBuf;
+use tauri::AppHandle;
+use tracing::info;
+
+/// Finds the WAV file of a stopped or recovered recording.
+fn recording_file(app: &AppHandle, recording_id: &str) -> Result<PathBuf> {
+    let dir = recordings_dir(app).map_err(|e| TranscriptionError::ReadError(e.to_string()))?;
+    [
+        recording_path(&dir, recording_id),
+        recovered_path(&dir, recording_id),
+    ]
+    .into_iter()
+    .find(|path| path.is_file())
+    .ok_or_else(|| TranscriptionError::RecordingNotFound(recording_id.to_string()))
+}
+
+/// Transcribes a stopped or recovered recording with an OpenAI-compatible
+/// API, reading it from disk instead of through the webview. It's uploaded as
+/// 16-bit PCM, so 25MB holds about 13 minutes of audio.
+///
+/// The audio comes from the saved file rather than the `RecordingSession`:
+/// stopping moves the audio out of the session, long recordings are written
+/// to disk while they're captured instead of being held in memory, and
+/// recovered recordings never had a session at all.
+#[tauri::command]
+pub async fn transcribe_recording(
+    app: AppHandle,
+    recording_id: String,
+    endpoint: OpenAiEndpoint,
+    options: Option<TranscriptionOptions>,
+) -> Result<String> {
+    let path = recording_file(&app, &recording_id)?;
+    let wav = tauri::async_runtime::spawn_blocking(move || {
+        read_wav_pcm16(&path).map_err(|e| TranscriptionError::ReadError(e.to_string()))
+    })
+    .await
+    .map_err(|e| TranscriptionError::ReadError(e.to_string()))??;
+    let text = openai::transcribe(&endpoint, wav, &options.unwrap_or_default()).await?;
+    info!(
+        "Transcribed recording {} with {} ({} characters)",
+        recording_id,
+        endpoint.model,
+        text.len()
+    );
+    Ok(text)
+}
+
+/// Transcribes a stopped or recovered recording on the CPU with a local
+/// whisper.cpp model, or the default model when none is given. The
+/// recorder's 16kHz mono samples are passed to the model as-is, without
+/// re-encoding.
+#[tauri::command]
+pub async fn transcribe_local(
+    app: AppHandle,
+    recording_id: String,
+    model_id: Option<String>,
+    options: Option<TranscriptionOptions>,
+) -> Result<String> {
+    let path = recording_file(&app, &recording_id)?;
+    let model_path = match &model_id {
+        Some(model_id) => models::installed_model_path(&app, model_id)?,
+        None => models::default_model_path(&app)?,
+    };
+    let options = options.unwrap_or_default();
+    let model = model_path.clone();
+    let text = tauri::async_runtime::spawn_blocking(move || {
+        let audio =
+            read_wav_file(&path).map_err(|e| TranscriptionError::ReadError(e.to_string()))?;
+        if audio.sample_rate != TARGET_SAMPLE_RATE || audio.channels != 1 {
+            return Err(TranscriptionError::ReadError(format!(
+                "Expected 16kHz mono audio, got {}Hz with {} channels",
+                audio.sample_rate, audio.channels
+            )));
+        }
+        local::transcribe(&model, &audio.samples, &options)
+    })
+    .await
+    .map_err(|e| TranscriptionError::InferenceError(e.to_string()))??;
+    info!(
+        "Transcribed recording {} locally with {} ({} characters)",
+        recording_id,
+        model_path.display(),
+        text.len()
+    );
+    Ok(text)
+}
+
+/// Lists the downloadable whisper.cpp models along with imported ones.
+#[tauri::command]
+pub fn list_transcription_models(app: AppHandle) -> Result<Vec<ModelInfo>> {
+    models::list_models(&app)
+}
+
+/// Downloads a model from the catalog, emitting `model-download-progress`.
+#[tauri::command]
+pub async fn download_transcription_model(app: AppHandle, model_id: String) -> Result<()> {
+    models::download_model(&app, &model_id).await?;
+    Ok(())
+}
+
+/// Copies a ggml model file into the models directory and returns its ID.
+/// Pass `sha256` to reject a file that doesn't match a published checksum.
+#[tauri::command]
+pub async fn import_transcription_model(
+    app: AppHandle,
+    path: PathBuf,
+    sha256: Option<String>,
+) -> Result<String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        models::import_model(&app, &path, sha256.as_deref())
+    })
+    .await
+    .map_err(|e| TranscriptionError::ModelError(e.to_string()))?
+}
+
+/// Deletes an installed model. Clears the default if it was the default.
+#[tauri::command]
+pub async fn delete_transcription_model(app: AppHandle, model_id: String) -> Result<()> {
+    tauri::async_runtime::spawn_blocking(move || models::delete_model(&app, &model_id))
+        .await
+        .map_err(|e| TranscriptionError::ModelError(e.to_string()))?
+}
+
+/// Checks an installed model against the checksum recorded when it was
+/// installed and returns its SHA-256.
+#[tauri::command]
+pub async fn verify_transcription_model(app: AppHandle, model_id: String) -> Result<String> {
+    tauri::async_runtime::spawn_blocking(move || models::verify_model(&app, &model_id))
+        .await
+        .map_err(|e| TranscriptionError::ModelError(e.to_string()))?
+}
+
+/// Sets the model `transcribe_local` uses when none is given, or clears it.
+#[tauri::command]
+pub fn set_default_transcription_model(app: AppHandle, model_id: Option<String>) -> Result<()> {
+    models::set_default_model(&app, model_id.as_deref())
+}
+
+/// Reports how much disk space the models directory takes up.
+#[tauri::command]
+pub fn transcription_models_disk_usage(app: AppHandle) -> Result<ModelsDiskUsage> {
+    models::disk_usage(&app)
+}
@@ -0,0 +1,70 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TranscriptionError {
+    #[error("Recording not found: {0}")]
+    RecordingNotFound(String),
+    #[error("Failed to read recording: {0}")]
+    ReadError(String),
+    #[error("The recording is {size_mb:.1}MB, more than the {max_mb}MB the service accepts")]
+    FileTooLarge { size_mb: f64, max_mb: u64 },
+    #[error("Failed to reach the transcription service: {0}")]
+    RequestError(String),
+    #[error("The transcription service returned {status}: {message}")]
+    ApiError { status: u16, message: String },
+    #[error("Unexpected response from the transcription service: {0}")]
+    InvalidResponse(String),
+    #[error("Model not found: {0}")]
+    ModelNotFound(String),
+    #[error("Failed to manage model: {0}")]
+    ModelError(String),
+    #[error("Invalid model: {0}")]
+    InvalidModel(String),
+    #[error("This build doesn't include local transcription")]
+    LocalUnavailable,
+    #[error("Local transcription failed: {0}")]
+    InferenceError(String),
+    #[error("Checksum mismatch for model {model_id}: expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        model_id: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("No default model is set")]
+    NoDefaultModel,
+}
+
+impl TranscriptionError {
+    /// Machine-readable code the frontend can match on.
+    pub fn code(&self) -> &'static str {
+        match self {
+            TranscriptionError::RecordingNotFound(_) => "RECORDING_NOT_FOUND",
+            TranscriptionError::ReadError(_) => "READ_FAILED",
+            TranscriptionError::FileTooLarge { .. } => "FILE_TOO_LARGE",
+            TranscriptionError::RequestError(_) => "REQUEST_FAILED",
+            TranscriptionError::ApiError { status: 401, .. } => "UNAUTHORIZED",
+            TranscriptionError::ApiError { .. } => "API_ERROR",
+            TranscriptionError::InvalidResponse(_) => "INVALID_RESPONSE",
+            TranscriptionError::ModelNotFound(_) => "MODEL_NOT_FOUND",
+            TranscriptionError::ModelError(_) => "MODEL_FAILED",
+            TranscriptionError::InvalidModel(_) => "INVALID_MODEL",
+            TranscriptionError::LocalUnavailable => "LOCAL_TRANSCRIPTION_UNAVAILABLE",
+            TranscriptionError::InferenceError(_) => "TRANSCRIPTION_FAILED",
+            TranscriptionError::ChecksumMismatch { .. } => "CHECKSUM_MISMATCH",
+            TranscriptionError::NoDefaultModel => "NO_DEFAULT_MODEL",
+        }
+    }
+}
+
+/// Serialized as `{ "code": "API_ERROR", "message": "..." }`.
+impl Serialize for TranscriptionError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("TranscriptionError", 2)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.end()
+    }
+}
+
+pub type Result<T> = std::result::Result<T, TranscriptionError>;
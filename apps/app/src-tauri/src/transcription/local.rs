@@ -0,0 +1,105 @@
+use super::error::{Result, TranscriptionError};
+use super::options::TranscriptionOptions;
+use std::path::Path;
+
+/// whisper.cpp stops getting faster beyond this many threads.
+#[cfg(feature = "local-transcription")]
+const MAX_THREADS: usize = 8;
+
+/// The last model loaded, kept so back-to-back dictations don't reload it.
+#[cfg(feature = "local-transcription")]
+static CONTEXT: once_cell::sync::Lazy<
+    std::sync::Mutex<Option<(std::path::PathBuf, whisper_rs::WhisperContext)>>,
+> = once_cell::sync::Lazy::new(|| std::sync::Mutex::new(None));
+
+/// Transcribes 16kHz mono samples on the CPU with the ggml model at
+/// `model_path`. Blocks for as long as inference takes.
+#[cfg(feature = "local-transcription")]
+pub fn transcribe(
+    model_path: &Path,
+    samples: &[f32],
+    options: &TranscriptionOptions,
+) -> Result<String> {
+    use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+    if samples.is_empty() {
+        return Ok(String::new());
+    }
+
+    let mut cached = CONTEXT.lock().unwrap_or_else(|e| e.into_inner());
+    // Free the previous model before loading the next one
+    let current = cached.take().filter(|(path, _)| path == model_path);
+    let (_, context) = match current {
+        Some(current) => cached.insert(current),
+        None => {
+            let path = model_path.to_str().ok_or_else(|| {
+                TranscriptionError::ModelError(format!(
+                    "Invalid model path: {}",
+                    model_path.display()
+                ))
+            })?;
+            let loaded = WhisperContext::new_with_params(path, WhisperContextParameters::default())
+                .map_err(|e| TranscriptionError::ModelError(e.to_string()))?;
+            tracing::info!("Loaded whisper model {}", model_path.display());
+            cached.insert((model_path.to_path_buf(), loaded))
+        }
+    };
+    let mut state = context
+        .create_state()
+        .map_err(|e| TranscriptionError::InferenceError(e.to_string()))?;
+
+    let threads = std::thread::available_parallelism()
+        .map(|threads| threads.get().min(MAX_THREADS))
+        .unwrap_or(1);
+    let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+    params.set_language(Some(options.language().unwrap_or("auto")));
+    let prompt = options.prompt();
+    if let Some(prompt) = &prompt {
+        params.set_initial_prompt(prompt);
+    }
+    if let Some(temperature) = options.temperature {
+        params.set_temperature(temperature);
+    }
+    params.set_n_threads(threads as _);
+    params.set_print_special(false);
+    params.set_print_progress(false);
+    params.set_print_realtime(false);
+    params.set_print_timestamps(false);
+
+    state
+        .full(params, samples)
+        .map_err(|e| TranscriptionError::InferenceError(e.to_string()))?;
+    let segments = state
+        .full_n_segments()
+        .map_err(|e| TranscriptionError::InferenceError(e.to_string()))?;
+    let mut text = String::new();
+    for segment in 0..segments {
+        let segment = state
+            .full_get_segment_text(segment)
+            .map_err(|e| TranscriptionError::InferenceError(e.to_string()))?;
+        text.push_str(&segment);
+    }
+    Ok(text.trim().to_string())
+}
+
+#[cfg(not(feature = "local-transcription"))]
+pub fn transcribe(
+    _model_path: &Path,
+    _samples: &[f32],
+    _options: &TranscriptionOptions,
+) -> Result<String> {
+    Err(TranscriptionError::LocalUnavailable)
+}
+
+/// Frees the cached model if it was loaded from `model_path`, e.g. before the
+/// file is deleted or replaced.
+#[cfg(feature = "local-transcription")]
+pub fn unload(model_path: &Path) {
+    let mut context = CONTEXT.lock().unwrap_or_else(|e| e.into_inner());
+    if context.as_ref().map(|(path, _)| path.as_path()) == Some(model_path) {
+        *context = None;
+    }
+}
+
+#[cfg(not(feature = "local-transcription"))]
+pub fn unload(_model_path: &Path) {}
@@ -0,0 +1,93 @@
+use super::error::{Result, TranscriptionError};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::warn;
+
+/// Kept in the models directory next to the models it describes.
+const MANIFEST_FILE: &str = "manifest.json";
+
+const HASH_BUFFER_BYTES: usize = 1024 * 1024;
+
+/// Serializes read-modify-write cycles, e.g. a download finishing while the
+/// user changes the default model.
+static MANIFEST_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// What we know about the installed models beyond the files themselves.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ModelManifest {
+    pub default_model: Option<String>,
+    /// Hex SHA-256 of each model, recorded when it was installed
+    pub checksums: BTreeMap<String, String>,
+}
+
+impl ModelManifest {
+    fn load(dir: &Path) -> Self {
+        let path = dir.join(MANIFEST_FILE);
+        let Ok(contents) = std::fs::read(&path) else {
+            return Self::default();
+        };
+        serde_json::from_slice(&contents).unwrap_or_else(|e| {
+            // Losing the default and checksums beats refusing to list models
+            warn!(
+                "Ignoring unreadable model manifest {}: {}",
+                path.display(),
+                e
+            );
+            Self::default()
+        })
+    }
+
+    fn save(&self, dir: &Path) -> Result<()> {
+        let path = dir.join(MANIFEST_FILE);
+        let partial = path.with_extension("json.part");
+        let contents = serde_json::to_vec_pretty(self)
+            .map_err(|e| TranscriptionError::ModelError(e.to_string()))?;
+        std::fs::write(&partial, contents)
+            .and_then(|_| std::fs::rename(&partial, &path))
+            .map_err(|e| TranscriptionError::ModelError(e.to_string()))
+    }
+}
+
+/// Returns a snapshot of the manifest in `dir`.
+pub fn read(dir: &Path) -> ModelManifest {
+    let _lock = MANIFEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    ModelManifest::load(dir)
+}
+
+/// Applies `change` to the manifest in `dir` and saves it.
+pub fn update<T>(dir: &Path, change: impl FnOnce(&mut ModelManifest) -> T) -> Result<T> {
+    let _lock = MANIFEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut manifest = ModelManifest::load(dir);
+    let result = change(&mut manifest);
+    manifest.save(dir)?;
+    Ok(result)
+}
+
+/// Copies `reader` into `writer`, returning the hex SHA-256 of what was copied.
+pub fn copy_hashed(reader: &mut impl Read, writer: &mut impl Write) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; HASH_BUFFER_BYTES];
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        hasher.update(&buffer[..read]);
+        writer.write_all(&buffer[..read])?;
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Hex SHA-256 of the file at `path`.
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    copy_hashed(&mut File::open(path)?, &mut std::io::sink())
+}
@@ -0,0 +1,17 @@
+pub mod commands;
+pub mod error;
+pub mod local;
+pub mod manifest;
+pub mod models;
+pub mod openai;
+pub mod options;
+
+pub use commands::{
+    delete_transcription_model, download_transcription_model, import_transcription_model,
+    list_transcription_models, set_default_transcription_model, transcribe_local,
+    transcribe_recording, transcription_models_disk_usage, verify_transcription_model,
+};
+pub use error::TranscriptionError;
+pub use models::{ModelInfo, ModelsDiskUsage};
+pub use openai::OpenAiEndpoint;
+pub use options::TranscriptionOptions;
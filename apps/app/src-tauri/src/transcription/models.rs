@@ -0,0 +1,495 @@
+use super::error::{Result, TranscriptionError};
+use super::local;
+use super::manifest;
+use crate::recorder::events::emit;
+use crate::recorder::spill::file_stem;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_http::reqwest::Client;
+use tracing::info;
+
+/// Directory under the app data dir that whisper.cpp models are kept in.
+const MODELS_DIR: &str = "models";
+
+const DOWNLOAD_BASE_URL: &str = "https://huggingface.co/ggerganov/whisper.cpp/resolve/main";
+
+/// Every ggml model file starts with this magic number, stored little-endian.
+const GGML_MAGIC: [u8; 4] = 0x6767_6d6c_u32.to_le_bytes();
+
+/// Progress events are emitted at most once per this many bytes.
+const PROGRESS_INTERVAL_BYTES: u64 = 1024 * 1024;
+
+/// Downloads can take minutes, so only the connection has a deadline.
+static DOWNLOAD_CLIENT: Lazy<Client> = Lazy::new(|| {
+    Client::builder()
+        .connect_timeout(Duration::from_secs(30))
+        .build()
+        .unwrap_or_default()
+});
+
+/// Emitted while a model downloads.
+pub const MODEL_DOWNLOAD_PROGRESS_EVENT: &str = "model-download-progress";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelDownloadProgress {
+    pub model_id: String,
+    pub downloaded_bytes: u64,
+    /// Unset when the server doesn't report a length
+    pub total_bytes: Option<u64>,
+}
+
+/// A model published in the whisper.cpp repository.
+pub struct CatalogModel {
+    pub id: &'static str,
+    pub size_mb: u64,
+    /// Hex SHA-256 published alongside the file
+    pub sha256: &'static str,
+}
+
+/// Models offered for download, smallest and fastest first within each size.
+/// The `.en` models only handle English; `q5_1` models are quantized.
+pub const CATALOG: &[CatalogModel] = &[
+    CatalogModel {
+        id: "tiny-q5_1",
+        size_mb: 31,
+        sha256: "818710568da3ca15689e31a743197b520007872ff9576237bda97bd1b469c3d7",
+    },
+    CatalogModel {
+        id: "tiny.en",
+        size_mb: 75,
+        sha256: "921e4cf8686fdd993dcd081a5da5b6c365bfde1162e72b08d75ac75289920b1f",
+    },
+    CatalogModel {
+        id: "tiny",
+        size_mb: 75,
+        sha256: "be07e048e1e599ad46341c8d2a135645097a538221678b7acdd1b1919c6e1b21",
+    },
+    CatalogModel {
+        id: "base-q5_1",
+        size_mb: 57,
+        sha256: "422f1ae452ade6f30a004d7e5c6a43195e4433bc370bf23fac9cc591f01a8898",
+    },
+    CatalogModel {
+        id: "base.en",
+        size_mb: 142,
+        sha256: "a03779c86df3323075f5e796cb2ce5029f00ec8869eee3fdfb897afe36c6d002",
+    },
+    CatalogModel {
+        id: "base",
+        size_mb: 142,
+        sha256: "60ed5bc3dd14eea856493d334349b405782ddcaf0028d4b5df4088345fba2efe",
+    },
+    CatalogModel {
+        id: "small-q5_1",
+        size_mb: 181,
+        sha256: "ae85e4a935d7a567bd102fe55afc16bb595bdb618e11b2fc7591bc08120411bb",
+    },
+    CatalogModel {
+        id: "small.en",
+        size_mb: 466,
+        sha256: "c6138d6d58ecc8322097e0f987c32f1be8bb0a18532a3f88f734d1bbf9c41e5d",
+    },
+    CatalogModel {
+        id: "small",
+        size_mb: 466,
+        sha256: "1be3a9b2063867b937e64e2ec7483364a79917e157fa98c5d94b5c1fffea987b",
+    },
+];
+
+fn catalog_model(model_id: &str) -> Option<&'static CatalogModel> {
+    CATALOG.iter().find(|model| model.id == model_id)
+}
+
+/// A catalog model, or one imported by the user.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelInfo {
+    pub id: String,
+    /// Set once the model is on disk
+    pub path: Option<PathBuf>,
+    /// Size on disk, or the approximate download size
+    pub size_bytes: u64,
+    pub in_catalog: bool,
+    /// Hex SHA-256 recorded when the model was installed
+    pub sha256: Option<String>,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelsDiskUsage {
+    pub dir: PathBuf,
+    /// Everything in the directory, including unfinished downloads
+    pub total_bytes: u64,
+}
+
+/// Creates the models directory if needed and returns it.
+pub fn models_dir(app: &AppHandle) -> std::io::Result<PathBuf> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(std::io::Error::other)?
+        .join(MODELS_DIR);
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Models are stored under the file names whisper.cpp publishes them with.
+pub fn model_path(dir: &Path, model_id: &str) -> PathBuf {
+    dir.join(format!("ggml-{}.bin", model_file_stem(model_id)))
+}
+
+/// Like [`file_stem`], but keeping the dots of names like `base.en`.
+fn model_file_stem(model_id: &str) -> String {
+    model_id
+        .split('.')
+        .map(file_stem)
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// The ID of a model stored at `path`.
+fn model_id(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    let id = name.strip_prefix("ggml-")?.strip_suffix(".bin")?;
+    Some(id.to_string())
+}
+
+fn io_error(e: impl ToString) -> TranscriptionError {
+    TranscriptionError::ModelError(e.to_string())
+}
+
+/// Rejects files that aren't ggml models before whisper.cpp tries to load them.
+fn check_ggml_magic(path: &Path) -> Result<()> {
+    let mut magic = [0u8; 4];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .map_err(io_error)?;
+    if magic != GGML_MAGIC {
+        return Err(TranscriptionError::InvalidModel(format!(
+            "{} is not a whisper.cpp (ggml) model",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Lists the catalog along with any imported models.
+pub fn list_models(app: &AppHandle) -> Result<Vec<ModelInfo>> {
+    let dir = models_dir(app).map_err(io_error)?;
+    let manifest = manifest::read(&dir);
+    let mut models: Vec<ModelInfo> = CATALOG
+        .iter()
+        .map(|model| ModelInfo {
+            id: model.id.to_string(),
+            path: None,
+            size_bytes: model.size_mb * 1024 * 1024,
+            in_catalog: true,
+            sha256: None,
+            is_default: false,
+        })
+        .collect();
+
+    for entry in std::fs::read_dir(&dir).map_err(io_error)? {
+        let path = entry.map_err(io_error)?.path();
+        let Some(id) = model_id(&path) else {
+            continue;
+        };
+        let size_bytes = std::fs::metadata(&path).map_err(io_error)?.len();
+        let sha256 = manifest.checksums.get(&id).cloned();
+        let is_default = manifest.default_model.as_deref() == Some(id.as_str());
+        match models.iter_mut().find(|model| model.id == id) {
+            Some(model) => {
+                model.path = Some(path);
+                model.size_bytes = size_bytes;
+                model.sha256 = sha256;
+                model.is_default = is_default;
+            }
+            None => models.push(ModelInfo {
+                id,
+                path: Some(path),
+                size_bytes,
+                in_catalog: false,
+                sha256,
+                is_default,
+            }),
+        }
+    }
+    Ok(models)
+}
+
+/// Returns the path of a model that is on disk.
+pub fn installed_model_path(app: &AppHandle, model_id: &str) -> Result<PathBuf> {
+    let path = model_path(&models_dir(app).map_err(io_error)?, model_id);
+    if !path.is_file() {
+        return Err(TranscriptionError::ModelNotFound(model_id.to_string()));
+    }
+    Ok(path)
+}
+
+/// Returns the path of the default model.
+pub fn default_model_path(app: &AppHandle) -> Result<PathBuf> {
+    let dir = models_dir(app).map_err(io_error)?;
+    let model_id = manifest::read(&dir)
+        .default_model
+        .ok_or(TranscriptionError::NoDefaultModel)?;
+    installed_model_path(app, &model_id)
+}
+
+/// Makes `model_id` the model used when none is given, or clears the default.
+pub fn set_default_model(app: &AppHandle, model_id: Option<&str>) -> Result<()> {
+    if let Some(model_id) = model_id {
+        installed_model_path(app, model_id)?;
+    }
+    let dir = models_dir(app).map_err(io_error)?;
+    manifest::update(&dir, |manifest| {
+        manifest.default_model = model_id.map(str::to_string);
+    })
+}
+
+/// Deletes an installed model, along with its checksum and default setting.
+pub fn delete_model(app: &AppHandle, model_id: &str) -> Result<()> {
+    let path = installed_model_path(app, model_id)?;
+    local::unload(&path);
+    std::fs::remove_file(&path).map_err(io_error)?;
+    let dir = models_dir(app).map_err(io_error)?;
+    manifest::update(&dir, |manifest| {
+        manifest.checksums.remove(model_id);
+        if manifest.default_model.as_deref() == Some(model_id) {
+            manifest.default_model = None;
+        }
+    })?;
+    info!("Deleted whisper model {}", model_id);
+    Ok(())
+}
+
+/// Hashes an installed model and compares it with the published checksum for
+/// catalog models, or else the one recorded when it was imported. Imported
+/// models copied into the directory by hand have none, so their checksum is
+/// recorded now. Returns the hex SHA-256.
+pub fn verify_model(app: &AppHandle, model_id: &str) -> Result<String> {
+    let path = installed_model_path(app, model_id)?;
+    let actual = manifest::sha256_file(&path).map_err(io_error)?;
+    let dir = models_dir(app).map_err(io_error)?;
+    let expected = manifest::update(&dir, |manifest| match catalog_model(model_id) {
+        Some(model) => {
+            if actual == model.sha256 {
+                manifest
+                    .checksums
+                    .insert(model_id.to_string(), actual.clone());
+            }
+            model.sha256.to_string()
+        }
+        None => manifest
+            .checksums
+            .entry(model_id.to_string())
+            .or_insert_with(|| actual.clone())
+            .clone(),
+    })?;
+    if expected != actual {
+        return Err(TranscriptionError::ChecksumMismatch {
+            model_id: model_id.to_string(),
+            expected,
+            actual,
+        });
+    }
+    Ok(actual)
+}
+
+/// Space taken up by the models directory.
+pub fn disk_usage(app: &AppHandle) -> Result<ModelsDiskUsage> {
+    let dir = models_dir(app).map_err(io_error)?;
+    let mut total_bytes = 0;
+    for entry in std::fs::read_dir(&dir).map_err(io_error)? {
+        let metadata = entry.and_then(|entry| entry.metadata()).map_err(io_error)?;
+        if metadata.is_file() {
+            total_bytes += metadata.len();
+        }
+    }
+    Ok(ModelsDiskUsage { dir, total_bytes })
+}
+
+/// Downloads a catalog model unless it's already on disk, emitting
+/// [`MODEL_DOWNLOAD_PROGRESS_EVENT`] as it goes.
+pub async fn download_model(app: &AppHandle, model_id: &str) -> Result<PathBuf> {
+    let model = catalog_model(model_id)
+        .ok_or_else(|| TranscriptionError::ModelNotFound(model_id.to_string()))?;
+    let dir = models_dir(app).map_err(io_error)?;
+    let path = model_path(&dir, model_id);
+    if path.is_file() {
+        return Ok(path);
+    }
+
+    let url = format!("{}/ggml-{}.bin", DOWNLOAD_BASE_URL, model_id);
+    info!("Downloading whisper model {} from {}", model_id, url);
+    let mut response = DOWNLOAD_CLIENT
+        .get(&url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| TranscriptionError::RequestError(e.to_string()))?;
+
+    // Download next to the final file so a failed download is never mistaken for a model
+    let partial = path.with_extension("bin.part");
+    let mut file = File::create(&partial).map_err(io_error)?;
+    let total_bytes = response.content_length();
+    let mut hasher = Sha256::new();
+    let mut downloaded_bytes = 0;
+    let mut reported_bytes = 0;
+    let downloaded: Result<String> = async {
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| TranscriptionError::RequestError(e.to_string()))?
+        {
+            file.write_all(&chunk).map_err(io_error)?;
+            hasher.update(&chunk);
+            downloaded_bytes += chunk.len() as u64;
+            if downloaded_bytes - reported_bytes >= PROGRESS_INTERVAL_BYTES {
+                reported_bytes = downloaded_bytes;
+                emit(
+                    app,
+                    MODEL_DOWNLOAD_PROGRESS_EVENT,
+                    ModelDownloadProgress {
+                        model_id: model_id.to_string(),
+                        downloaded_bytes,
+                        total_bytes,
+                    },
+                );
+            }
+        }
+        file.sync_all().map_err(io_error)?;
+        check_ggml_magic(&partial)?;
+        let actual = format!("{:x}", hasher.finalize());
+        if actual != model.sha256 {
+            return Err(TranscriptionError::ChecksumMismatch {
+                model_id: model_id.to_string(),
+                expected: model.sha256.to_string(),
+                actual,
+            });
+        }
+        Ok(actual)
+    }
+    .await;
+    drop(file);
+
+    let sha256 = match downloaded {
+        Ok(sha256) => sha256,
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            return Err(e);
+        }
+    };
+    std::fs::rename(&partial, &path).map_err(io_error)?;
+    manifest::update(&dir, |manifest| {
+        manifest.checksums.insert(model_id.to_string(), sha256);
+    })?;
+    info!(
+        "Downloaded whisper model {} ({} bytes)",
+        model_id, downloaded_bytes
+    );
+    Ok(path)
+}
+
+/// Copies a ggml model file from `source` into the models directory and
+/// returns its ID, derived from the file name. When `expected_sha256` is
+/// given, a copy that doesn't match it is thrown away.
+pub fn import_model(
+    app: &AppHandle,
+    source: &Path,
+    expected_sha256: Option<&str>,
+) -> Result<String> {
+    check_ggml_magic(source)?;
+    let name = source
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default();
+    let model_id = model_file_stem(name.strip_prefix("ggml-").unwrap_or(name));
+    if model_id.is_empty() {
+        return Err(TranscriptionError::InvalidModel(format!(
+            "Can't name a model after {}",
+            source.display()
+        )));
+    }
+
+    let dir = models_dir(app).map_err(io_error)?;
+    let path = model_path(&dir, &model_id);
+    let partial = path.with_extension("bin.part");
+    let copied = File::open(source)
+        .and_then(|mut reader| {
+            let mut file = File::create(&partial)?;
+            let sha256 = manifest::copy_hashed(&mut reader, &mut file)?;
+            file.sync_all()?;
+            Ok(sha256)
+        })
+        .map_err(io_error)
+        .and_then(|actual| match expected_sha256.map(str::trim) {
+            Some(expected) if !expected.eq_ignore_ascii_case(&actual) => {
+                Err(TranscriptionError::ChecksumMismatch {
+                    model_id: model_id.clone(),
+                    expected: expected.to_ascii_lowercase(),
+                    actual,
+                })
+            }
+            _ => Ok(actual),
+        });
+    let sha256 = match copied {
+        Ok(sha256) => sha256,
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            return Err(e);
+        }
+    };
+
+    // A model being replaced may still be loaded
+    local::unload(&path);
+    std::fs::rename(&partial, &path).map_err(io_error)?;
+    manifest::update(&dir, |manifest| {
+        manifest.checksums.insert(model_id.clone(), sha256);
+    })?;
+    info!(
+        "Imported whisper model {} from {}",
+        model_id,
+        source.display()
+    );
+    Ok(model_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catalog_checksums_are_sha256_hex() {
+        for model in CATALOG {
+            assert_eq!(model.sha256.len(), 64, "{}", model.id);
+            assert!(
+                model
+                    .sha256
+                    .bytes()
+                    .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b)),
+                "{}",
+                model.id
+            );
+        }
+    }
+
+    #[test]
+    fn model_files_keep_their_published_names() {
+        let dir = Path::new("models");
+        assert_eq!(model_path(dir, "base.en"), dir.join("ggml-base.en.bin"));
+        assert_eq!(model_path(dir, "../tiny"), dir.join("ggml-tiny.bin"));
+        assert_eq!(
+            model_id(&dir.join("ggml-small-q5_1.bin")).as_deref(),
+            Some("small-q5_1")
+        );
+    }
+}
@@ -0,0 +1,293 @@
+use super::error::{Result, TranscriptionError};
+use super::options::TranscriptionOptions;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::time::Duration;
+use tauri_plugin_http::reqwest::multipart::{Form, Part};
+use tauri_plugin_http::reqwest::Client;
+use tracing::debug;
+
+/// Long recordings can take a while to transcribe.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+
+static CLIENT: Lazy<Client> = Lazy::new(|| {
+    Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .unwrap_or_default()
+});
+
+/// Any server implementing OpenAI's `/audio/transcriptions` endpoint, such as
+/// OpenAI, Groq or faster-whisper-server.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenAiEndpoint {
+    /// API root, e.g. `https://api.openai.com/v1`
+    #[serde(default = "default_base_url")]
+    pub base_url: String,
+    pub api_key: Option<String>,
+    #[serde(default = "default_model")]
+    pub model: String,
+    /// Largest upload the service accepts; OpenAI rejects anything over
+    /// 25MB. `null` for servers without a limit.
+    #[serde(default = "default_max_file_size_mb")]
+    pub max_file_size_mb: Option<u64>,
+}
+
+fn default_base_url() -> String {
+    "https://api.openai.com/v1".to_string()
+}
+
+fn default_model() -> String {
+    "whisper-1".to_string()
+}
+
+fn default_max_file_size_mb() -> Option<u64> {
+    Some(25)
+}
+
+#[derive(Deserialize)]
+struct ApiErrorBody {
+    message: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ApiResponse {
+    Text { text: String },
+    Error { error: ApiErrorBody },
+}
+
+/// Posts a WAV file to `endpoint` and returns the transcribed text.
+pub async fn transcribe(
+    endpoint: &OpenAiEndpoint,
+    wav: Vec<u8>,
+    options: &TranscriptionOptions,
+) -> Result<String> {
+    let size_mb = wav.len() as f64 / (1024.0 * 1024.0);
+    if let Some(max_mb) = endpoint.max_file_size_mb {
+        if size_mb > max_mb as f64 {
+            return Err(TranscriptionError::FileTooLarge { size_mb, max_mb });
+        }
+    }
+
+    let file = Part::bytes(wav)
+        .file_name("recording.wav")
+        .mime_str("audio/wav")
+        .map_err(|e| TranscriptionError::RequestError(e.to_string()))?;
+    let mut form = Form::new()
+        .part("file", file)
+        .text("model", endpoint.model.clone());
+    if let Some(language) = options.language() {
+        form = form.text("language", language.to_string());
+    }
+    if let Some(prompt) = options.prompt() {
+        form = form.text("prompt", prompt);
+    }
+    if let Some(temperature) = options.temperature {
+        form = form.text("temperature", temperature.to_string());
+    }
+
+    let url = format!(
+        "{}/audio/transcriptions",
+        endpoint.base_url.trim_end_matches('/')
+    );
+    debug!("Posting {:.1}MB recording to {}", size_mb, url);
+    let mut request = CLIENT.post(&url).multipart(form);
+    if let Some(api_key) = endpoint.api_key.as_deref().filter(|key| !key.is_empty()) {
+        request = request.bearer_auth(api_key);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| TranscriptionError::RequestError(e.to_string()))?;
+
+    let status = response.status();
+    let body = response
+        .text()
+        .await
+        .map_err(|e| TranscriptionError::RequestError(e.to_string()))?;
+    match serde_json::from_str::<ApiResponse>(&body) {
+        Ok(ApiResponse::Text { text }) if status.is_success() => Ok(text.trim().to_string()),
+        Ok(ApiResponse::Error { error }) => Err(TranscriptionError::ApiError {
+            status: status.as_u16(),
+            message: error.message,
+        }),
+        _ if !status.is_success() => Err(TranscriptionError::ApiError {
+            status: status.as_u16(),
+            message: body,
+        }),
+        _ => Err(TranscriptionError::InvalidResponse(body)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    /// Answers a single request on a local port with `status` and `body`.
+    /// The join handle returns the raw request.
+    fn serve_once(status: &'static str, body: &'static str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}/v1/", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request = Vec::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                request.extend_from_slice(line.as_bytes());
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut body_bytes = vec![0; content_length];
+            reader.read_exact(&mut body_bytes).unwrap();
+            request.extend_from_slice(&body_bytes);
+
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+            String::from_utf8_lossy(&request).into_owned()
+        });
+        (base_url, handle)
+    }
+
+    fn endpoint(base_url: String) -> OpenAiEndpoint {
+        OpenAiEndpoint {
+            base_url,
+            api_key: Some("sk-test".to_string()),
+            model: "whisper-large-v3".to_string(),
+            max_file_size_mb: default_max_file_size_mb(),
+        }
+    }
+
+    fn transcribe_with(
+        endpoint: &OpenAiEndpoint,
+        wav: Vec<u8>,
+        options: &TranscriptionOptions,
+    ) -> Result<String> {
+        tauri::async_runtime::block_on(transcribe(endpoint, wav, options))
+    }
+
+    #[test]
+    fn posts_the_form_with_bearer_auth() {
+        let (base_url, server) = serve_once("200 OK", r#"{"text":" Hallo Welt \n"}"#);
+        let options = TranscriptionOptions {
+            language: Some("de".to_string()),
+            prompt: Some("Names: {{vocabulary}}".to_string()),
+            vocabulary: Some("Ada, Grace".to_string()),
+            temperature: Some(0.2),
+        };
+        let text = transcribe_with(&endpoint(base_url), b"RIFF".to_vec(), &options).unwrap();
+        let request = server.join().unwrap();
+
+        assert_eq!(text, "Hallo Welt");
+        assert!(
+            request.starts_with("POST /v1/audio/transcriptions "),
+            "{}",
+            request
+        );
+        assert!(request
+            .to_ascii_lowercase()
+            .contains("authorization: bearer sk-test\r\n"));
+        for (name, value) in [
+            ("model", "whisper-large-v3"),
+            ("language", "de"),
+            ("prompt", "Names: Ada, Grace"),
+            ("temperature", "0.2"),
+        ] {
+            let field = format!("name=\"{}\"\r\n\r\n{}\r\n", name, value);
+            assert!(request.contains(&field), "missing {} in {}", name, request);
+        }
+        assert!(request.contains("filename=\"recording.wav\""));
+    }
+
+    #[test]
+    fn unset_options_are_left_out() {
+        let (base_url, server) = serve_once("200 OK", r#"{"text":"hi"}"#);
+        let mut endpoint = endpoint(base_url);
+        endpoint.api_key = None;
+        transcribe_with(&endpoint, Vec::new(), &TranscriptionOptions::default()).unwrap();
+        let request = server.join().unwrap().to_ascii_lowercase();
+
+        assert!(!request.contains("authorization:"));
+        for name in ["language", "prompt", "temperature"] {
+            assert!(!request.contains(&format!("name=\"{}\"", name)));
+        }
+    }
+
+    #[test]
+    fn api_errors_keep_the_status_and_message() {
+        let (base_url, server) = serve_once(
+            "401 Unauthorized",
+            r#"{"error":{"message":"Invalid API key"}}"#,
+        );
+        let error = transcribe_with(&endpoint(base_url), Vec::new(), &Default::default());
+        server.join().unwrap();
+
+        let error = error.unwrap_err();
+        assert_eq!(error.code(), "UNAUTHORIZED");
+        assert!(matches!(
+            error,
+            TranscriptionError::ApiError { status: 401, ref message } if message == "Invalid API key"
+        ));
+    }
+
+    #[test]
+    fn error_pages_are_passed_through() {
+        let (base_url, server) = serve_once("502 Bad Gateway", "upstream timed out");
+        let error = transcribe_with(&endpoint(base_url), Vec::new(), &Default::default());
+        server.join().unwrap();
+
+        assert!(matches!(
+            error,
+            Err(TranscriptionError::ApiError { status: 502, ref message }) if message == "upstream timed out"
+        ));
+    }
+
+    #[test]
+    fn success_without_text_is_invalid() {
+        let (base_url, server) = serve_once("200 OK", r#"{"segments":[]}"#);
+        let error = transcribe_with(&endpoint(base_url), Vec::new(), &Default::default());
+        server.join().unwrap();
+
+        assert!(matches!(error, Err(TranscriptionError::InvalidResponse(_))));
+    }
+
+    #[test]
+    fn size_limit_is_checked_before_uploading() {
+        let mut endpoint = endpoint("http://127.0.0.1:9/v1".to_string());
+        endpoint.max_file_size_mb = Some(1);
+        let error = transcribe_with(&endpoint, vec![0; 2 * 1024 * 1024], &Default::default());
+        assert!(matches!(
+            error,
+            Err(TranscriptionError::FileTooLarge { max_mb: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn size_limit_can_be_turned_off() {
+        let (base_url, server) = serve_once("200 OK", r#"{"text":"long"}"#);
+        let mut endpoint = endpoint(base_url);
+        endpoint.max_file_size_mb = None;
+        let text = transcribe_with(&endpoint, vec![0; 2 * 1024 * 1024], &Default::default());
+        server.join().unwrap();
+
+        assert_eq!(text.unwrap(), "long");
+    }
+}
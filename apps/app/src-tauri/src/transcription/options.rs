@@ -0,0 +1,43 @@
+use serde::Deserialize;
+
+/// Placeholder in the prompt that is replaced with the custom vocabulary.
+const VOCABULARY_PLACEHOLDER: &str = "{{vocabulary}}";
+
+/// Settings shared by every transcription backend. Fields left out by the
+/// frontend keep their defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TranscriptionOptions {
+    /// ISO-639-1 code of the spoken language, or "auto" to detect it
+    pub language: Option<String>,
+    /// Text that guides the model's style and spelling. May contain
+    /// `{{vocabulary}}`.
+    pub prompt: Option<String>,
+    /// Words the model should favor, e.g. names and jargon
+    pub vocabulary: Option<String>,
+    /// Sampling temperature from 0.0 to 1.0
+    pub temperature: Option<f32>,
+}
+
+impl TranscriptionOptions {
+    /// The language to force, if any.
+    pub fn language(&self) -> Option<&str> {
+        self.language
+            .as_deref()
+            .map(str::trim)
+            .filter(|language| !language.is_empty() && *language != "auto")
+    }
+
+    /// The prompt with the vocabulary filled in. Without a prompt the
+    /// vocabulary alone is used, which still biases the model towards it.
+    pub fn prompt(&self) -> Option<String> {
+        let vocabulary = self.vocabulary.as_deref().unwrap_or_default().trim();
+        let prompt = self.prompt.as_deref().unwrap_or_default().trim();
+        let prompt = if prompt.is_empty() {
+            vocabulary.to_string()
+        } else {
+            prompt.replace(VOCABULARY_PLACEHOLDER, vocabulary)
+        };
+        Some(prompt).filter(|prompt| !prompt.is_empty())
+    }
+}
@@ -3,7 +3,10 @@ import {
 	playSoundIfEnabled,
 	userConfiguredServices,
 } from '$lib/services/index.js';
-import type { UpdateStatusMessageFn } from '$lib/services/recorder/RecorderService';
+import {
+	describeAutoStop,
+	type UpdateStatusMessageFn,
+} from '$lib/services/recorder/RecorderService';
 import { toast } from '$lib/services/toast';
 import { settings } from '$lib/stores/settings.svelte';
 import { noop } from '@tanstack/table-core';
@@ -67,6 +70,13 @@ function createRecorder({
 			const startRecordingResult =
 				await userConfiguredServices.recorder.startRecording(nanoid(), {
 					sendStatus: (options) => toast.loading({ id: toastId, ...options }),
+					onAutoStopped: (event) => {
+						toast.warning({
+							id: toastId,
+							title: '⏹️ Recording stopped automatically',
+							description: `${describeAutoStop(event)} Stop the recording to keep what was captured.`,
+						});
+					},
 				});
 			return startRecordingResult;
 		},
@@ -15,6 +15,8 @@ import { createClipboardServiceDesktop } from './clipboard/ClipboardService.desk
 import { createHttpServiceDesktop } from './http/HttpService.desktop';
 // import { createRecorderServiceTauri } from './recorder/RecorderService.tauri';
 import { createRecorderServiceWeb } from './recorder/RecorderService.web';
+// import { createVadServiceTauri } from './recorder/VadService.tauri';
+import { createVadServiceWeb } from './recorder/VadService.web';
 import { createRunTransformationService } from './runTransformation';
 import { createPlaySoundServiceDesktop } from './sound/PlaySoundService.desktop';
 import { createFasterWhisperServerTranscriptionService } from './transcription/TranscriptionService.fasterWhisperServer';
@@ -102,6 +104,8 @@ export const RunTransformationService = createRunTransformationService({
 export const userConfiguredServices = (() => {
 	// const RecorderServiceTauri = createRecorderServiceTauri();
 	const RecorderServiceWeb = createRecorderServiceWeb();
+	// const VadServiceTauri = createVadServiceTauri();
+	const VadServiceWeb = createVadServiceWeb();
 
 	return {
 		get transcription() {
@@ -142,6 +146,13 @@ export const userConfiguredServices = (() => {
 			// }
 			return RecorderServiceWeb;
 		},
+		get vad() {
+			// Hands-free dictation listens on the same device pipeline as the recorder
+			// if (settings.value['recorder.selectedRecorderService'] === 'Tauri') {
+			// 	return VadServiceTauri;
+			// }
+			return VadServiceWeb;
+		},
 	};
 })();
 
@@ -1,9 +1,21 @@
 import { Err, Ok, tryAsync } from '@epicenterhq/result';
 import { WhisperingErr, type WhisperingRecordingState } from '@repo/shared';
 import { invoke as tauriInvoke } from '@tauri-apps/api/core';
-import type { RecorderService } from './RecorderService';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import { readFile } from '@tauri-apps/plugin-fs';
+import type {
+	AudioChunk,
+	RecorderService,
+	RecordingAutoStopped,
+} from './RecorderService';
 
 export function createRecorderServiceTauri(): RecorderService {
+	let unlistenAutoStopped: UnlistenFn | undefined;
+	const stopListeningForAutoStop = () => {
+		unlistenAutoStopped?.();
+		unlistenAutoStopped = undefined;
+	};
+
 	return {
 		getRecorderState: async () => {
 			const result =
@@ -42,12 +54,13 @@ export function createRecorderServiceTauri(): RecorderService {
 					'Initializing your recording session and checking microphone access...',
 			});
 			const result = await invoke('init_recording_session', {
-				deviceName: settings.deviceId,
+				deviceId: settings.deviceId,
 			});
 			if (!result.ok)
 				return WhisperingErr({
 					title: '🎤 Unable to Start Recording Session',
 					description:
+						describeRecorderError(result.error.error) ??
 						'We encountered an issue while setting up your recording session. This could be because your microphone is being used by another app, your microphone permissions are denied, or the selected recording device is disconnected',
 					action: { type: 'more-details', error: result.error },
 				});
@@ -69,21 +82,44 @@ export function createRecorderServiceTauri(): RecorderService {
 				});
 			return Ok(undefined);
 		},
-		startRecording: async (recordingId, { sendStatus: sendUpdateStatus }) => {
+		startRecording: async (
+			recordingId,
+			{ sendStatus: sendUpdateStatus, onAudioChunk, onAutoStopped },
+		) => {
 			sendUpdateStatus({
 				title: '🎯 Starting Up',
 				description: 'Preparing your microphone and initializing recording...',
 			});
+			const unlisten = onAudioChunk
+				? await listen<AudioChunk>('recording-audio-chunk', ({ payload }) => {
+						onAudioChunk(payload);
+						if (payload.isFinal) unlisten?.();
+					})
+				: undefined;
+			stopListeningForAutoStop();
+			unlistenAutoStopped = await listen<RecordingAutoStopped>(
+				'recording-auto-stopped',
+				({ payload }) => {
+					if (payload.recordingId !== recordingId) return;
+					stopListeningForAutoStop();
+					onAutoStopped?.(payload);
+				},
+			);
 			const result = await invoke<void>('start_recording', {
 				recordingId,
+				streamChunks: onAudioChunk !== undefined,
 			});
-			if (!result.ok)
+			if (!result.ok) {
+				unlisten?.();
+				stopListeningForAutoStop();
 				return WhisperingErr({
 					title: '🎤 Recording Start Failed',
 					description:
+						describeRecorderError(result.error.error) ??
 						'Unable to start recording. Please check your microphone and try again.',
 					action: { type: 'more-details', error: result.error },
 				});
+			}
 			return Ok(undefined);
 		},
 		stopRecording: async ({ sendStatus: sendUpdateStatus }) => {
@@ -92,16 +128,33 @@ export function createRecorderServiceTauri(): RecorderService {
 				description:
 					'Saving your recording and preparing the final audio file...',
 			});
-			const result = await invoke<number[]>('stop_recording');
+			stopListeningForAutoStop();
+			const result = await invoke<RecordingFile>('stop_recording');
 			if (!result.ok)
 				return WhisperingErr({
 					title: '⏹️ Recording Stop Failed',
-					description: 'Unable to save your recording. Please try again.',
+					description:
+						describeRecorderError(result.error.error) ??
+						'Unable to save your recording. Please try again.',
 					action: { type: 'more-details', error: result.error },
 				});
 
-			const float32Array = new Float32Array(result.data);
-			const blob = createWavFromFloat32(float32Array);
+			const fileResult = await tryAsync({
+				try: () => readFile(result.data.path),
+				mapErr: (error) =>
+					WhisperingErr({
+						title: '⏹️ Recording Stop Failed',
+						description: `Your recording was saved to ${result.data.path}, but it could not be read back.`,
+						action: { type: 'more-details', error },
+					}),
+			});
+			if (!fileResult.ok) return fileResult;
+			// The blob is all we need from here on; a file left behind is
+			// cleaned up on the next launch after a week
+			void invoke('delete_recording', {
+				recordingId: result.data.recordingId,
+			});
+			const blob = new Blob([fileResult.data], { type: 'audio/wav' });
 			return Ok(blob);
 		},
 		cancelRecording: async ({ sendStatus: sendUpdateStatus }) => {
@@ -110,6 +163,7 @@ export function createRecorderServiceTauri(): RecorderService {
 				description:
 					'Safely stopping your recording and cleaning up resources...',
 			});
+			stopListeningForAutoStop();
 			const result = await invoke('cancel_recording');
 			if (!result.ok)
 				return WhisperingErr({
@@ -123,59 +177,66 @@ export function createRecorderServiceTauri(): RecorderService {
 	};
 }
 
-async function invoke<T>(command: string, args?: Record<string, unknown>) {
-	return tryAsync({
-		try: async () => await tauriInvoke<T>(command, args),
-		mapErr: (error) =>
-			Err({ _tag: 'TauriInvokeError', command, error } as const),
-	});
-}
-
-function createWavFromFloat32(float32Array: Float32Array, sampleRate = 96000) {
-	// WAV header parameters
-	const numChannels = 1; // Mono
-	const bitsPerSample = 32;
-	const bytesPerSample = bitsPerSample / 8;
-
-	// Calculate sizes
-	const dataSize = float32Array.length * bytesPerSample;
-	const headerSize = 44; // Standard WAV header size
-	const totalSize = headerSize + dataSize;
-
-	// Create the buffer
-	const buffer = new ArrayBuffer(totalSize);
-	const view = new DataView(buffer);
+/** A finished recording saved in the app data dir. */
+type RecordingFile = {
+	recordingId: string;
+	path: string;
+	durationSecs: number;
+	/** Offsets in seconds at which the recording was paused */
+	segmentMarkers: number[];
+};
 
-	// Write WAV header
-	// "RIFF" chunk descriptor
-	writeString(view, 0, 'RIFF');
-	view.setUint32(4, totalSize - 8, true);
-	writeString(view, 8, 'WAVE');
+/** Error shape returned by every recorder command. */
+type RecorderError = { code: string; message: string };
 
-	// "fmt " sub-chunk
-	writeString(view, 12, 'fmt ');
-	view.setUint32(16, 16, true); // Subchunk1Size (16 for PCM)
-	view.setUint16(20, 3, true); // AudioFormat (3 for Float)
-	view.setUint16(22, numChannels, true);
-	view.setUint32(24, sampleRate, true);
-	view.setUint32(28, sampleRate * numChannels * bytesPerSample, true); // ByteRate
-	view.setUint16(32, numChannels * bytesPerSample, true); // BlockAlign
-	view.setUint16(34, bitsPerSample, true);
+const isRecorderError = (error: unknown): error is RecorderError =>
+	typeof error === 'object' &&
+	error !== null &&
+	'code' in error &&
+	'message' in error;
 
-	// "data" sub-chunk
-	writeString(view, 36, 'data');
-	view.setUint32(40, dataSize, true);
-
-	// Write audio data
-	const dataView = new Float32Array(buffer, headerSize);
-	dataView.set(float32Array);
-
-	// Create and return blob
-	return new Blob([buffer], { type: 'audio/wav' });
+/** Targeted remedies for the recorder failures users can fix themselves. */
+export function describeRecorderError(error: unknown): string | undefined {
+	if (!isRecorderError(error)) return undefined;
+	switch (error.code) {
+		case 'DEVICE_NOT_FOUND':
+			return 'The selected microphone could not be found. Plug it back in or pick another device in the recording settings.';
+		case 'PERMISSION_DENIED':
+			return 'Microphone access was denied. Allow microphone access for Whispering in your system privacy settings and try again.';
+		case 'DEVICE_UNAVAILABLE':
+			return 'The selected microphone is unavailable. It may have been disconnected or be in use by another app.';
+		case 'UNSUPPORTED_CONFIG':
+			return 'The selected microphone does not support a usable recording format. Try another device.';
+		case 'RECORDING_FILE_FAILED':
+			return 'Your recording could not be saved to disk. Check that there is enough free space and try again.';
+		case 'STREAM_BUILD_FAILED':
+		case 'STREAM_START_FAILED':
+			return 'The microphone could not be opened. Close other apps that may be using it and try again.';
+		case 'AUDIO_THREAD_TIMEOUT':
+			return 'The audio system stopped responding. Try again, or restart the recorder.';
+		case 'NO_SESSION':
+			return 'No recording session is active. Please try again to start a new one.';
+		case 'ALREADY_RECORDING':
+			return 'A recording is already in progress.';
+		case 'NOT_RECORDING':
+			return 'There is no recording in progress.';
+		case 'ALREADY_PAUSED':
+			return 'The recording is already paused.';
+		case 'NOT_PAUSED':
+			return 'The recording is not paused.';
+		case 'DUPLICATE_RECORDING':
+			return 'A recording with this ID is already in progress.';
+		case 'RECORDING_NOT_FOUND':
+			return 'No recording with this ID is in progress.';
+		default:
+			return undefined;
+	}
 }
 
-function writeString(view: DataView, offset: number, string: string) {
-	for (let i = 0; i < string.length; i++) {
-		view.setUint8(offset + i, string.charCodeAt(i));
-	}
+export async function invoke<T>(command: string, args?: Record<string, unknown>) {
+	return tryAsync({
+		try: async () => await tauriInvoke<T>(command, args),
+		mapErr: (error) =>
+			Err({ _tag: 'TauriInvokeError', command, error } as const),
+	});
 }
@@ -9,6 +9,34 @@ export type UpdateStatusMessageFn = (args: {
 	description: string;
 }) => void;
 
+/** 16 kHz mono PCM emitted by the native recorder while streaming. */
+export type AudioChunk = {
+	sequence: number;
+	sampleRate: number;
+	samples: number[];
+	isFinal: boolean;
+};
+
+/** Sent when the native recorder stops capturing on its own. The audio up to
+ * that point is kept until the recording is stopped. */
+export type RecordingAutoStopped = { recordingId: string } & (
+	| { reason: 'silence'; silenceSecs: number }
+	| { reason: 'durationLimit'; maxDurationSecs: number }
+	| { reason: 'memoryLimit'; maxBytes: number }
+);
+
+/** Tells the user why a recording stopped capturing on its own. */
+export function describeAutoStop(event: RecordingAutoStopped): string {
+	switch (event.reason) {
+		case 'silence':
+			return `Recording stopped after ${Math.round(event.silenceSecs)} seconds of silence.`;
+		case 'durationLimit':
+			return `Recording stopped after reaching its ${Math.round(event.maxDurationSecs / 60)} minute limit.`;
+		case 'memoryLimit':
+			return `Recording stopped after reaching its ${Math.round(event.maxBytes / 1_000_000)} MB limit.`;
+	}
+}
+
 export type RecordingSessionSettings = {
 	deviceId: string | null;
 	bitsPerSecond: number;
@@ -30,7 +58,13 @@ export type RecorderService = {
 	}) => Promise<WhisperingResult<void>>;
 	startRecording: (
 		recordingId: string,
-		callbacks: { sendStatus: UpdateStatusMessageFn },
+		callbacks: {
+			sendStatus: UpdateStatusMessageFn;
+			/** Opt-in: receive audio in chunks while recording. */
+			onAudioChunk?: (chunk: AudioChunk) => void;
+			/** Called when the recording stops capturing on its own. */
+			onAutoStopped?: (event: RecordingAutoStopped) => void;
+		},
 	) => Promise<WhisperingResult<void>>;
 	stopRecording: (callbacks: {
 		sendStatus: UpdateStatusMessageFn;
@@ -0,0 +1,132 @@
+import { Ok } from '@epicenterhq/result';
+import { WhisperingErr, type WhisperingRecordingState } from '@repo/shared';
+import { utils } from '@ricky0123/vad-web';
+import { type UnlistenFn, listen } from '@tauri-apps/api/event';
+import { toast } from '../toast';
+import { describeRecorderError, invoke } from './RecorderService.tauri';
+
+type SpeechEnd = {
+	segment: number;
+	startSecs: number;
+	sampleRate: number;
+	samples: number[];
+};
+
+/**
+ * Hands-free dictation on the native recorder, so speech is detected on the
+ * same cpal device as regular recordings instead of a separate browser
+ * microphone.
+ */
+export function createVadServiceTauri() {
+	let unlistenFns: UnlistenFn[] | null = null;
+	let isActivelyListening = false;
+
+	const stopListening = () => {
+		for (const unlisten of unlistenFns ?? []) unlisten();
+		unlistenFns = null;
+		isActivelyListening = false;
+	};
+
+	const closeVad = async () => {
+		if (!unlistenFns) return Ok(undefined);
+		// The session can't be closed while it's listening
+		if (isActivelyListening) await invoke<void>('cancel_recording');
+		const result = await invoke<void>('close_recording_session');
+		stopListening();
+		if (!result.ok)
+			return WhisperingErr({
+				title: 'Failed to close Voice Activity Detector',
+				description:
+					describeRecorderError(result.error.error) ??
+					'Unable to close the recording session used by the VAD.',
+				action: { type: 'more-details', error: result.error },
+			});
+		return Ok(undefined);
+	};
+
+	return {
+		getVadState: (): WhisperingRecordingState => {
+			if (!unlistenFns) return 'IDLE';
+			if (isActivelyListening) return 'SESSION+RECORDING';
+			return 'SESSION';
+		},
+		ensureVad: async ({
+			onSpeechEnd,
+			deviceId,
+		}: {
+			onSpeechEnd: (blob: Blob) => void;
+			deviceId: string | null;
+		}) => {
+			if (unlistenFns) return Ok(undefined);
+			const result = await invoke('init_recording_session', {
+				deviceId: deviceId ?? 'default',
+			});
+			if (!result.ok)
+				return WhisperingErr({
+					title: 'Failed to initialize Voice Activity Detector',
+					description:
+						describeRecorderError(result.error.error) ??
+						'Unable to open the selected microphone for voice activity detection.',
+					action: { type: 'more-details', error: result.error },
+				});
+			unlistenFns = await Promise.all([
+				listen('speech-start', () => {
+					toast.success({
+						title: '🎙️ Speech started',
+						description: 'Recording started. Speak clearly and loudly.',
+					});
+				}),
+				listen<SpeechEnd>('speech-end', ({ payload }) => {
+					const wavBuffer = utils.encodeWAV(
+						new Float32Array(payload.samples),
+						3,
+						payload.sampleRate,
+					);
+					onSpeechEnd(new Blob([wavBuffer], { type: 'audio/wav' }));
+				}),
+			]);
+			return Ok(undefined);
+		},
+		closeVad,
+		startVad: async () => {
+			if (!unlistenFns)
+				return WhisperingErr({
+					title: 'Voice Activity Detector not initialized',
+					description:
+						'The voice activity detector has not been initialized. Please ensure that the VAD is initialized before starting it.',
+				});
+			const result = await invoke<void>('start_recording', { vad: {} });
+			if (!result.ok)
+				return WhisperingErr({
+					title: 'Failed to start Voice Activity Detector',
+					description:
+						describeRecorderError(result.error.error) ??
+						'An unknown error occurred while starting the VAD.',
+					action: { type: 'more-details', error: result.error },
+				});
+			isActivelyListening = true;
+			return Ok(undefined);
+		},
+		pauseVad: async () => {
+			if (!unlistenFns)
+				return WhisperingErr({
+					title: 'Voice Activity Detector not initialized',
+					description: 'VAD not initialized',
+				});
+			// Ending the recording emits a final speech-end for any segment still
+			// in progress. The full recording itself isn't needed.
+			const result = await invoke<void>('cancel_recording');
+			if (!result.ok)
+				return WhisperingErr({
+					title: 'Failed to pause Voice Activity Detector',
+					description:
+						describeRecorderError(result.error.error) ??
+						'An unknown error occurred while pausing the VAD.',
+					action: { type: 'more-details', error: result.error },
+				});
+			isActivelyListening = false;
+			return Ok(undefined);
+		},
+		destroyVad: closeVad,
+	};
+}